@@ -0,0 +1,242 @@
+use yonmokun::evaluation::{self, AnalysisReport, SearchLimits};
+use yonmokun::game::Game;
+use yonmokun::json;
+use yonmokun::position::Player;
+use yonmokun::record;
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+
+/// The limits used by `yonmokun analyse` when none are given on the command line.
+pub const DEFAULT_LIMITS: SearchLimits = SearchLimits { depth: 6, time_budget: None, threads: 1 };
+
+const CSV_HEADER: &str = "line,input,to_move,best_move,evaluation,confidence,principal_variation,depth,nodes,time_ms,error";
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Csv,
+    Json
+}
+
+
+/// Analyses each position read from `reader`, one position string or move list per line, writing a row of results
+/// for each to `writer` as it goes. Blank lines and lines starting with `#` are skipped. A line that cannot be
+/// analysed gets a row with an error instead of results; the number of such lines is returned.
+///
+/// Input that starts with a game record, as the game saves them, is read as a file of records instead: the current
+/// position of each game is analysed, and its row gives the line the record starts on and the position string.
+pub fn analyse_all<R: BufRead, W: Write>(mut reader: R, writer: &mut W, limits: SearchLimits, format: OutputFormat) -> io::Result<usize> {
+    let mut output = Output { writer, format, num_rows: 0, num_errors: 0 };
+    output.start()?;
+
+    let mut skipped = String::new();
+    let mut line = String::new();
+    let mut line_number = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        };
+        line_number += 1;
+
+        let input = line.trim();
+        if input.is_empty() || input.starts_with('#') {
+            skipped.push_str(&line);
+            continue;
+        };
+
+        if output.num_rows == 0 && input == "Game" {
+            skipped.push_str(&line);
+            reader.read_to_string(&mut skipped)?;
+            analyse_records(&skipped, &mut output, limits)?;
+            break;
+        };
+
+        output.write(Row { line: line_number, input, result: analyse_line(input, limits) })?;
+    }
+
+    output.finish()
+}
+
+
+fn analyse_records<W: Write>(records: &str, output: &mut Output<W>, limits: SearchLimits) -> io::Result<()> {
+    let mut game_lines = records.lines().enumerate().filter(|(_, line)| line.trim() == "Game").map(|(i, _)| i+1);
+
+    match record::read_games(records.as_bytes()) {
+        Ok(saved_games) => for (line, saved_game) in game_lines.zip(saved_games) {
+            let position = saved_game.game.position();
+            let result = evaluation::analyse_with_limits(position, limits)
+                .map(|report| (position.active_player, report))
+                .map_err(|e| e.to_string());
+            output.write(Row { line, input: &position.to_string(), result })?;
+        },
+        Err(e) => {
+            let line = game_lines.next().unwrap_or(1);
+            output.write(Row { line, input: "Game", result: Err(e.to_string()) })?;
+        }
+    };
+
+    Ok(())
+}
+
+
+fn analyse_line(input: &str, limits: SearchLimits) -> Result<(Player, AnalysisReport), String> {
+    let game = Game::from_position_or_moves(input).map_err(|e| e.to_string())?;
+    let position = game.position();
+    let report = evaluation::analyse_with_limits(position, limits).map_err(|e| e.to_string())?;
+    Ok((position.active_player, report))
+}
+
+
+struct Output<'w, W: Write> {
+    writer: &'w mut W,
+    format: OutputFormat,
+    num_rows: usize,
+    num_errors: usize
+}
+
+impl<W: Write> Output<'_, W> {
+    fn start(&mut self) -> io::Result<()> {
+        match self.format {
+            OutputFormat::Csv => writeln!(self.writer, "{}", CSV_HEADER),
+            OutputFormat::Json => write!(self.writer, "[")
+        }
+    }
+
+    fn write(&mut self, row: Row) -> io::Result<()> {
+        if row.result.is_err() {
+            self.num_errors += 1;
+        };
+
+        match self.format {
+            OutputFormat::Csv => writeln!(self.writer, "{}", row.to_csv())?,
+            OutputFormat::Json => write!(self.writer, "{}\n  {}", if self.num_rows == 0 { "" } else { "," }, row.to_json())?
+        };
+        self.writer.flush()?;
+        self.num_rows += 1;
+        Ok(())
+    }
+
+    /// Ends the output, returning the number of rows with an error.
+    fn finish(self) -> io::Result<usize> {
+        if self.format == OutputFormat::Json {
+            writeln!(self.writer, "{}]", if self.num_rows == 0 { "" } else { "\n" })?;
+        };
+        Ok(self.num_errors)
+    }
+}
+
+
+struct Row<'a> {
+    line: usize,
+    input: &'a str,
+    result: Result<(Player, AnalysisReport), String>
+}
+
+impl Row<'_> {
+    fn to_csv(&self) -> String {
+        let (fields, error) = match &self.result {
+            Ok((player, report)) => ([
+                player.to_string(),
+                report.best_move.to_string(),
+                report.evaluation.to_string(),
+                format!("{:.3}", report.evaluation.to_confidence()),
+                principal_variation(report).join(" "),
+                report.depth.to_string(),
+                report.num_positions_traversed.to_string(),
+                millis(report.search_time)
+            ], String::new()),
+            Err(e) => (Default::default(), e.clone())
+        };
+
+        let mut columns = vec![self.line.to_string(), self.input.to_string()];
+        columns.extend(fields);
+        columns.push(error);
+        columns.iter().map(|column| csv_escape(column)).collect::<Vec<String>>().join(",")
+    }
+
+    fn to_json(&self) -> String {
+        match &self.result {
+            Ok((player, report)) => format!("{{\"line\": {}, \"input\": {}, \"to_move\": \"{}\", {}}}", self.line, json::string(self.input), player, json::report_members(report)),
+            Err(e) => format!("{{\"line\": {}, \"input\": {}, \"error\": {}}}", self.line, json::string(self.input), json::string(e))
+        }
+    }
+}
+
+
+fn principal_variation(report: &AnalysisReport) -> Vec<String> {
+    report.principal_variation.iter().map(|coordinates| coordinates.to_string()).collect()
+}
+
+fn millis(duration: Duration) -> String {
+    duration.as_millis().to_string()
+}
+
+fn csv_escape(s: &str) -> String {
+    match s.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", s.replace('"', "\"\"")),
+        false => s.to_string()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{analyse_all, csv_escape, OutputFormat, DEFAULT_LIMITS};
+    use yonmokun::evaluation::SearchLimits;
+
+    const INPUT: &str = "# study positions\na1 d4 a1 d4 a1 d4\n\na1 e5\n";
+
+    fn run(format: OutputFormat) -> (String, usize) {
+        let limits = SearchLimits { depth: 2, ..DEFAULT_LIMITS };
+        let mut output = Vec::new();
+        let num_errors = analyse_all(INPUT.as_bytes(), &mut output, limits, format).unwrap();
+        (String::from_utf8(output).unwrap(), num_errors)
+    }
+
+    #[test]
+    fn test_csv_output() {
+        let (output, num_errors) = run(OutputFormat::Csv);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(num_errors, 1);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("2,a1 d4 a1 d4 a1 d4,w,a1,win,1.000,a1,"));
+        assert!(lines[2].starts_with("4,a1 e5,,,,,,,,,"));
+
+        assert_eq!(csv_escape("a1 d4"), "a1 d4");
+        assert_eq!(csv_escape("move 2: \"e5\""), "\"move 2: \"\"e5\"\"\"");
+        assert_eq!(csv_escape("a1\r"), "\"a1\r\"");
+    }
+
+    #[test]
+    fn test_json_output() {
+        let (output, _) = run(OutputFormat::Json);
+
+        assert!(output.starts_with("[\n  {\"line\": 2, \"input\": \"a1 d4 a1 d4 a1 d4\", \"to_move\": \"w\", \"best_move\": \"a1\", \"evaluation\": \"win\""));
+        assert!(output.contains(",\n  {\"line\": 4, \"input\": \"a1 e5\", \"error\": "));
+        assert!(output.ends_with("}\n]\n"));
+    }
+
+    #[test]
+    fn test_records() {
+        let records = "# saved games\nGame\nSetting white human\nStart ................/................/................/................ w 0\nMove a1 w human 1697712345123 ?\nResult ongoing\nEnd\n\nGame\nStart ................/................/................/................ w 0\nResult ongoing\nEnd\n";
+        let limits = SearchLimits { depth: 2, ..DEFAULT_LIMITS };
+        let mut output = Vec::new();
+        let num_errors = analyse_all(records.as_bytes(), &mut output, limits, OutputFormat::Csv).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(num_errors, 0);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("2,w.............../................/................/................ b 1,b,"));
+        assert!(lines[2].starts_with("9,................/................/................/................ w 0,w,"));
+
+        let mut output = Vec::new();
+        let num_errors = analyse_all("Game\nMove a1 w human 0 ?\nMove e5 b human 0 ?\nEnd\n".as_bytes(), &mut output, limits, OutputFormat::Csv).unwrap();
+        assert_eq!(num_errors, 1);
+        assert!(String::from_utf8(output).unwrap().lines().nth(1).unwrap().starts_with("1,Game,,,,,,,,,\"line 3:"));
+    }
+}
@@ -0,0 +1,128 @@
+use yonmokun::position::{Coordinates, ParseCoordinatesError};
+use std::error::Error;
+use std::fmt;
+
+
+/// Something the user can type on their turn: either a move or one of the commands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// A move, with the floor it was annotated with if any.
+    Play(Coordinates, Option<usize>),
+    Undo,
+    Redo,
+    Hint,
+    Save(Option<String>),
+    Load(Option<String>),
+    Quit,
+    Help,
+    FlipView
+}
+
+
+/// Parses a line of user input. Moves can be given in notation ("b3", optionally with a floor as in "b3:2") or as
+/// a file number followed by a rank number ("23"). Commands have English names and Japanese aliases, and `save`
+/// and `load` take an optional file name.
+pub fn parse_command(input: &str) -> Result<Command, ParseCommandError> {
+    let input = input.trim();
+    let (name, argument) = match input.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, Some(argument.trim().to_string())),
+        None => (input, None)
+    };
+
+    if name.is_empty() {
+        return Err(ParseCommandError::Empty)
+    };
+
+    if looks_like_move(name) {
+        if argument.is_some() {
+            return Err(ParseCommandError::UnexpectedArgument(name.to_string()))
+        };
+        let (coordinates, floor) = parse_move(name).map_err(ParseCommandError::InvalidMove)?;
+        return Ok(Command::Play(coordinates, floor))
+    };
+
+    let command = match name.to_lowercase().as_str() {
+        "undo" | "u" | "待った" => Command::Undo,
+        "redo" | "r" | "進める" => Command::Redo,
+        "hint" | "h" | "ヒント" => Command::Hint,
+        "save" | "s" | "保存" => return Ok(Command::Save(argument)),
+        "load" | "l" | "読込" => return Ok(Command::Load(argument)),
+        "quit" | "q" | "終了" => Command::Quit,
+        "help" | "?" | "ヘルプ" => Command::Help,
+        "flip" | "f" | "反転" => Command::FlipView,
+        _ => return Err(ParseCommandError::UnknownCommand(name.to_string()))
+    };
+
+    match argument {
+        Some(_) => Err(ParseCommandError::UnexpectedArgument(name.to_string())),
+        None => Ok(command)
+    }
+}
+
+
+fn looks_like_move(s: &str) -> bool {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(first), _) if first.is_ascii_digit() => true,
+        (Some(first), Some(second)) => first.is_ascii_alphabetic() && second.is_ascii_digit(),
+        _ => false
+    }
+}
+
+
+fn parse_move(s: &str) -> Result<(Coordinates, Option<usize>), ParseCoordinatesError> {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(file @ '1'..='4') => {
+            let letter = (b'a' + (file as u8 - b'1')) as char;
+            Coordinates::parse_with_floor(&format!("{}{}", letter, chars.as_str()))
+        },
+        Some(c) if c.is_ascii_digit() => Err(ParseCoordinatesError::InvalidFile(c)),
+        _ => Coordinates::parse_with_floor(s)
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseCommandError {
+    Empty,
+    UnknownCommand(String),
+    UnexpectedArgument(String),
+    InvalidMove(ParseCoordinatesError)
+}
+
+impl fmt::Display for ParseCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseCommandError::Empty => write!(f, "no move or command given"),
+            ParseCommandError::UnknownCommand(name) => write!(f, "unknown command \"{}\"", name),
+            ParseCommandError::UnexpectedArgument(name) => write!(f, "\"{}\" does not take an argument", name),
+            ParseCommandError::InvalidMove(e) => write!(f, "{}", e)
+        }
+    }
+}
+
+impl Error for ParseCommandError {}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_command, Command, ParseCommandError};
+    use yonmokun::position::{Coordinates, ParseCoordinatesError};
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("b3"), Ok(Command::Play(Coordinates::new(1, 2), None)));
+        assert_eq!(parse_command(" 23 "), Ok(Command::Play(Coordinates::new(1, 2), None)));
+        assert_eq!(parse_command("41:3"), Ok(Command::Play(Coordinates::new(3, 0), Some(2))));
+        assert_eq!(parse_command("待った"), Ok(Command::Undo));
+        assert_eq!(parse_command("SAVE my game.txt"), Ok(Command::Save(Some("my game.txt".to_string()))));
+        assert_eq!(parse_command("load"), Ok(Command::Load(None)));
+
+        assert_eq!(parse_command(""), Err(ParseCommandError::Empty));
+        assert_eq!(parse_command("52"), Err(ParseCommandError::InvalidMove(ParseCoordinatesError::InvalidFile('5'))));
+        assert_eq!(parse_command("b7"), Err(ParseCommandError::InvalidMove(ParseCoordinatesError::InvalidRank('7'))));
+        assert_eq!(parse_command("undo 2"), Err(ParseCommandError::UnexpectedArgument("undo".to_string())));
+        assert_eq!(parse_command("resign"), Err(ParseCommandError::UnknownCommand("resign".to_string())));
+    }
+}
@@ -0,0 +1,191 @@
+use crate::evaluation::{self, AnalysisReport, SearchLimits, StopHandle};
+use crate::position::{Position, Evaluation, Coordinates, EngineError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+
+pub const MAX_LEVEL: u8 = 7;
+
+
+/// How strongly the engine plays. Besides searching less deeply, weaker levels pick their moves at random with a
+/// bias towards the better ones, and now and then overlook a threat they ought to block, so that they make the kind
+/// of mistakes a person would rather than simply playing short-sightedly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Difficulty {
+    pub level: u8,
+    pub limits: SearchLimits,
+    /// How freely the engine strays from its best move, in percentage points of confidence. Zero always plays the
+    /// best move.
+    pub temperature: f32,
+    /// The chance that the engine fails to notice when the opponent is threatening to win on their next move.
+    pub missed_block_chance: f32
+}
+
+impl Difficulty {
+    /// Returns the difficulty for a level from 1 to `MAX_LEVEL`.
+    pub fn level(level: u8) -> Option<Difficulty> {
+        let (depth, time_budget, temperature, missed_block_chance) = match level {
+            1 => (1, None, 8.0, 0.5),
+            2 => (2, None, 5.0, 0.3),
+            3 => (3, None, 3.0, 0.15),
+            4 => (4, None, 1.5, 0.05),
+            5 => (5, None, 0.0, 0.0),
+            6 => (6, None, 0.0, 0.0),
+            7 => (12, Some(Duration::from_secs(5)), 0.0, 0.0),
+            _ => return None
+        };
+
+        Some(Difficulty { level, limits: SearchLimits { depth, time_budget, threads: 1 }, temperature, missed_block_chance })
+    }
+
+    /// Whether the engine always plays the best move it finds, so that its choice depends on nothing but the search.
+    pub fn plays_best_move(&self) -> bool {
+        self.temperature <= 0.0 && self.missed_block_chance <= 0.0
+    }
+}
+
+
+/// Chooses a move at the given difficulty. The report gives the move chosen and the engine's evaluation of it, which
+/// at the weaker levels need not be the best move available. `on_progress` is passed the progress of the search, as
+/// in `evaluation::analyse_with_progress`. Returns an error if the game is already over.
+pub fn choose_move(position: &Position, difficulty: &Difficulty, rng: &mut Rng, on_progress: &mut dyn FnMut(&AnalysisReport)) -> Result<AnalysisReport, EngineError> {
+    if difficulty.plays_best_move() {
+        return evaluation::analyse_with_progress(position, difficulty.limits, &StopHandle::new(), on_progress)
+    };
+
+    // The weaker levels need a score for every move to choose between them, not just the best one.
+    let mut report = evaluation::analyse_every_move(position, difficulty.limits, &StopHandle::new(), on_progress)?;
+
+    // A threat is overlooked by judging each move only by the position it leaves, without looking at the replies.
+    let overlooks_threat = is_under_threat(position) && rng.next_f32() < difficulty.missed_block_chance;
+    let candidates: Vec<(Coordinates, Evaluation)> = match overlooks_threat {
+        true => report.move_evaluations.iter().map(|(coordinates, _)| (*coordinates, -position.play(*coordinates).get_static_evaluation())).collect(),
+        false => report.move_evaluations.clone()
+    };
+
+    let (best_move, evaluation) = sample_softmax(&candidates, difficulty.temperature, rng);
+    if best_move != report.best_move {
+        report.principal_variation = vec![best_move];
+    };
+    report.best_move = best_move;
+    report.evaluation = evaluation;
+
+    Ok(report)
+}
+
+
+/// Returns whether the opponent could complete a line on their next move if the player to move did nothing to stop
+/// them, as though the player to move were to pass.
+fn is_under_threat(position: &Position) -> bool {
+    let mut passed = position.clone();
+    passed.active_player = -passed.active_player;
+    passed.generate_moves().into_iter().any(|coordinates| passed.play(coordinates).get_static_evaluation() == Evaluation::Loss)
+}
+
+
+/// Picks a move with probability proportional to exp(confidence / temperature), with confidence in percent. A
+/// temperature of zero always picks the best move.
+fn sample_softmax(candidates: &[(Coordinates, Evaluation)], temperature: f32, rng: &mut Rng) -> (Coordinates, Evaluation) {
+    let best = *candidates.iter().max_by_key(|(_, evaluation)| *evaluation).unwrap();
+
+    if temperature <= 0.0 {
+        return best
+    };
+
+    let best_confidence = best.1.to_confidence() * 100.0;
+    let weights: Vec<f32> = candidates.iter()
+        .map(|(_, evaluation)| ((evaluation.to_confidence() * 100.0 - best_confidence) / temperature).exp())
+        .collect();
+
+    let mut remaining = rng.next_f32() * weights.iter().sum::<f32>();
+    for (candidate, weight) in candidates.iter().zip(weights) {
+        if remaining < weight {
+            return *candidate
+        };
+        remaining -= weight;
+    }
+
+    best
+}
+
+
+/// A small xorshift pseudo-random number generator, good enough for varying the engine's play.
+pub struct Rng {
+    state: u64
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed.max(1) }
+    }
+
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        Rng::new(nanos as u64)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// Returns a number in the range [0, 1).
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{choose_move, is_under_threat, Difficulty, Rng, MAX_LEVEL};
+    use crate::evaluation::MAX_DEPTH;
+    use crate::position::{Position, Coordinates};
+    use std::time::Duration;
+
+    #[test]
+    fn test_levels() {
+        assert!(Difficulty::level(0).is_none());
+        assert!(Difficulty::level(MAX_LEVEL + 1).is_none());
+
+        for level in 1..MAX_LEVEL {
+            let weaker = Difficulty::level(level).unwrap();
+            let stronger = Difficulty::level(level + 1).unwrap();
+            assert!(weaker.limits.depth <= stronger.limits.depth);
+            assert!(weaker.temperature >= stronger.temperature);
+            assert!(weaker.missed_block_chance >= stronger.missed_block_chance);
+        }
+    }
+
+    #[test]
+    fn test_weak_levels_still_take_wins() {
+        let mut position = Position::new();
+        for _ in 0..3 {
+            position = position.play(Coordinates::new(0, 0)).play(Coordinates::new(3, 3));
+        }
+
+        let mut rng = Rng::new(42);
+        let difficulty = Difficulty::level(1).unwrap();
+        let num_wins_taken = (0..20).filter(|_| choose_move(&position, &difficulty, &mut rng, &mut |_| {}).unwrap().best_move == Coordinates::new(0, 0)).count();
+        assert!(num_wins_taken >= 18);
+    }
+
+    #[test]
+    fn test_threats() {
+        let position = Position::new().play(Coordinates::new(0, 0)).play(Coordinates::new(3, 3)).play(Coordinates::new(0, 0)).play(Coordinates::new(3, 2)).play(Coordinates::new(0, 0));
+        assert!(is_under_threat(&position));
+        assert!(!is_under_threat(&position.play(Coordinates::new(0, 0))));
+    }
+
+    #[test]
+    fn test_weak_levels_keep_to_the_time_budget() {
+        let mut difficulty = Difficulty::level(2).unwrap();
+        difficulty.limits.depth = MAX_DEPTH;
+        difficulty.limits.time_budget = Some(Duration::from_millis(50));
+
+        let report = choose_move(&Position::new(), &difficulty, &mut Rng::new(42), &mut |_| {}).unwrap();
+        assert!(report.depth < MAX_DEPTH);
+        assert_eq!(report.move_evaluations.len(), 16);
+    }
+}
@@ -1,77 +1,358 @@
-use crate::position::{Position, Evaluation, Coordinates};
-use std::time::{Duration, Instant};
+use crate::position::{Position, Evaluation, Coordinates, EngineError};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
 
 
-pub fn analyse(position: &Position, depth: u8) -> AnalysisReport {
+/// The deepest a search can usefully go, since no game lasts longer than this. Searches with a time budget but no
+/// particular depth in mind deepen up to here.
+pub const MAX_DEPTH: u8 = 64;
+
+/// How often, in positions traversed, the search checks whether it has run out of time or been stopped.
+const INTERRUPTION_CHECK_INTERVAL: u32 = 1024;
+
+
+/// Searches the position to the given depth on a single thread. Returns an error if the game is already over, as do
+/// all the `analyse` functions.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn analyse(position: &Position, depth: u8) -> Result<AnalysisReport, EngineError> {
+    analyse_with_limits(position, SearchLimits { depth, time_budget: None, threads: 1 })
+}
+
+
+/// Searches to the depth given in the limits. If a time budget is also given, the search deepens one ply at a time
+/// and reports the result of the deepest search that finished within the budget.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn analyse_with_limits(position: &Position, limits: SearchLimits) -> Result<AnalysisReport, EngineError> {
+    analyse_until_stopped(position, limits, &StopHandle::new())
+}
+
+
+/// Searches as `analyse_with_limits` does, but gives up as soon as the search is stopped through the handle,
+/// reporting the result of the deepest search that finished. If not even a one-ply search finished, the report only
+/// reflects the static evaluation of each move, with a depth of zero.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn analyse_until_stopped(position: &Position, limits: SearchLimits, stop: &StopHandle) -> Result<AnalysisReport, EngineError> {
+    search(position, limits, &SystemClock::new(), stop, None, false)
+}
+
+
+/// Searches as `analyse_until_stopped` does, passing `on_progress` a report each time a search to a new depth
+/// finishes, with the time and positions traversed so far. Even without a time budget the search deepens one ply at
+/// a time, so that there is progress to report before it reaches the full depth.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn analyse_with_progress(position: &Position, limits: SearchLimits, stop: &StopHandle, on_progress: &mut dyn FnMut(&AnalysisReport)) -> Result<AnalysisReport, EngineError> {
+    analyse_with_clock(position, limits, &SystemClock::new(), stop, on_progress)
+}
+
+
+/// Searches as `analyse_with_progress` does, but scores every move from the root exactly instead of only proving the
+/// others no better than the best, filling in the report's `move_evaluations`. This takes longer than finding the
+/// best move alone.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn analyse_every_move(position: &Position, limits: SearchLimits, stop: &StopHandle, on_progress: &mut dyn FnMut(&AnalysisReport)) -> Result<AnalysisReport, EngineError> {
+    search(position, limits, &SystemClock::new(), stop, Some(on_progress), true)
+}
+
+
+/// Searches as `analyse_with_progress` does, telling the time by the given clock. The other `analyse` functions all
+/// use the system clock, which is not available everywhere; this is the one to use where it is not.
+pub fn analyse_with_clock(position: &Position, limits: SearchLimits, clock: &dyn Clock, stop: &StopHandle, on_progress: &mut dyn FnMut(&AnalysisReport)) -> Result<AnalysisReport, EngineError> {
+    search(position, limits, clock, stop, Some(on_progress), false)
+}
+
+
+/// The search itself, behind all the `analyse` functions.
+fn search(position: &Position, limits: SearchLimits, clock: &dyn Clock, stop: &StopHandle, mut on_progress: Option<&mut dyn FnMut(&AnalysisReport)>, score_every_move: bool) -> Result<AnalysisReport, EngineError> {
     if position.is_terminal() {
-        panic!("Cannot play on a terminal state.")
+        return Err(EngineError::TerminalPosition)
     };
 
-    let start_time = Instant::now();
-
+    let start_time = clock.now();
     let mut num_positions_traversed_including_root = 1;
+
+    let result = search_root(position, 0, Interruption::NEVER, limits.threads, score_every_move, &mut num_positions_traversed_including_root).unwrap();
+    let mut report = AnalysisReport {
+        evaluation: result.evaluation,
+        best_move: result.principal_variation[0],
+        principal_variation: result.principal_variation,
+        move_evaluations: result.move_evaluations,
+        depth: 0,
+        search_time: clock.now().saturating_sub(start_time),
+        num_positions_traversed: num_positions_traversed_including_root
+    };
+
+    // A budget too large to have a deadline, such as `Duration::MAX`, deepens until stopped.
+    let deadline = limits.time_budget.and_then(|time_budget| start_time.checked_add(time_budget)).map(|time| Deadline { clock, time });
+    let interruption = Interruption { deadline, stop: Some(stop) };
+    let depths = match (limits.time_budget, &on_progress) {
+        (None, None) => limits.depth..=limits.depth,
+        _ => 1..=limits.depth
+    };
+
+    for depth in depths {
+        if limits.time_budget.is_some() && matches!(report.evaluation, Evaluation::Win | Evaluation::Loss) {
+            break;
+        };
+
+        let result = search_root(position, depth, interruption, limits.threads, score_every_move, &mut num_positions_traversed_including_root);
+        report.search_time = clock.now().saturating_sub(start_time);
+        report.num_positions_traversed = num_positions_traversed_including_root;
+
+        match result {
+            Some(result) => {
+                report.evaluation = result.evaluation;
+                report.best_move = result.principal_variation[0];
+                report.principal_variation = result.principal_variation;
+                report.move_evaluations = result.move_evaluations;
+                report.depth = depth;
+                if let Some(on_progress) = on_progress.as_mut() {
+                    on_progress(&report);
+                };
+            },
+            None => break
+        }
+    }
+
+    Ok(report)
+}
+
+
+/// The outcome of searching every move from the root.
+struct RootResult {
+    evaluation: Evaluation,
+    principal_variation: Vec<Coordinates>,
+    /// Each move with its exact evaluation, if every move was scored, in the order the moves were searched.
+    move_evaluations: Vec<(Coordinates, Evaluation)>
+}
+
+
+/// Searches every move from the root, returning the best evaluation and the principal variation starting with the
+/// best move, or `None` if the search was interrupted before it finished. With more than one thread the root moves
+/// are dealt out between the threads, each of which searches its share with its own window.
+fn search_root(position: &Position, depth: u8, interruption: Interruption, threads: usize, score_every_move: bool, num_positions_traversed_so_far: &mut u32) -> Option<RootResult> {
+    let moves = position.generate_moves();
+    let threads = threads.clamp(1, moves.len().max(1));
+
+    if threads == 1 {
+        return search_moves(position, &moves, depth, interruption, score_every_move, num_positions_traversed_so_far)
+    };
+
+    let results: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads).map(|i| {
+            let share: Vec<Coordinates> = moves.iter().skip(i).step_by(threads).copied().collect();
+            scope.spawn(move || {
+                let mut num_positions_traversed = 0;
+                let result = search_moves(position, &share, depth, interruption, score_every_move, &mut num_positions_traversed);
+                (result, num_positions_traversed)
+            })
+        }).collect();
+
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    });
+
+    let order = |coordinates: Coordinates| moves.iter().position(|c| *c == coordinates);
+    let mut best: Option<RootResult> = None;
+    let mut move_evaluations = Vec::new();
+    for (result, num_positions_traversed) in results {
+        *num_positions_traversed_so_far += num_positions_traversed;
+        let result = result?;
+        move_evaluations.extend_from_slice(&result.move_evaluations);
+
+        // Ties go to the move that comes first in move order, as they would in a single-threaded search.
+        let is_better = match &best {
+            None => true,
+            Some(best) => result.evaluation > best.evaluation || (result.evaluation == best.evaluation && order(result.principal_variation[0]) < order(best.principal_variation[0]))
+        };
+        if is_better {
+            best = Some(result);
+        };
+    }
+
+    move_evaluations.sort_by_key(|(coordinates, _)| order(*coordinates));
+    best.map(|best| RootResult { move_evaluations, ..best })
+}
+
+
+/// Searches the given moves from the root, as `search_root` does. When scoring every move, each is searched with a
+/// full window rather than one narrowed by the best move so far.
+fn search_moves(position: &Position, moves: &[Coordinates], depth: u8, interruption: Interruption, score_every_move: bool, num_positions_traversed_so_far: &mut u32) -> Option<RootResult> {
     let mut best_eval_so_far = Evaluation::Loss;
-    let mut best_move_so_far = None;
+    let mut principal_variation = Vec::new();
+    let mut move_evaluations = Vec::new();
 
-    for coordinates in position.generate_moves() {
-        let new_position = position.play(coordinates);
-        let evaluation = -get_negamax_evaluation(&new_position, depth, &mut num_positions_traversed_including_root, Evaluation::Loss, -best_eval_so_far);
+    for &coordinates in moves {
+        let mut new_position = position.play(coordinates);
+        let mut continuation = Vec::new();
+        let beta = match score_every_move {
+            true => Evaluation::Win,
+            false => -best_eval_so_far
+        };
+        let evaluation = -get_negamax_evaluation(&mut new_position, depth, num_positions_traversed_so_far, interruption, Evaluation::Loss, beta, &mut continuation)?;
 
-        if best_move_so_far.is_none() {
-            best_move_so_far = Some(coordinates);
+        if score_every_move {
+            move_evaluations.push((coordinates, evaluation));
         };
 
-        if evaluation > best_eval_so_far {
+        if principal_variation.is_empty() || evaluation > best_eval_so_far {
             best_eval_so_far = evaluation;
-            best_move_so_far = Some(coordinates);
+            principal_variation = vec![coordinates];
+            principal_variation.append(&mut continuation);
         }
     }
 
-    AnalysisReport{
-        evaluation: best_eval_so_far,
-        best_move: best_move_so_far.unwrap(),
-        search_time: start_time.elapsed(),
-        num_positions_traversed: num_positions_traversed_including_root
-    }
+    Some(RootResult { evaluation: best_eval_so_far, principal_variation, move_evaluations })
 }
 
 
-fn get_negamax_evaluation(position: &Position, depth: u8, num_positions_traversed_so_far: &mut u32, mut alpha: Evaluation, beta: Evaluation) -> Evaluation {
+/// Returns the negamax evaluation of the position, filling `principal_variation` with the line of best play found
+/// whenever a move raises alpha.
+fn get_negamax_evaluation(position: &mut Position, depth: u8, num_positions_traversed_so_far: &mut u32, interruption: Interruption, mut alpha: Evaluation, beta: Evaluation, principal_variation: &mut Vec<Coordinates>) -> Option<Evaluation> {
     *num_positions_traversed_so_far += 1;
 
+    if num_positions_traversed_so_far.is_multiple_of(INTERRUPTION_CHECK_INTERVAL) && interruption.is_due() {
+        return None
+    };
+
     let static_evaluation = position.get_static_evaluation();
 
     match static_evaluation {
-        Evaluation::Win => Evaluation::Win,
-        Evaluation::Loss => Evaluation::Loss,
-        Evaluation::Draw => Evaluation::Draw,
+        Evaluation::Win => Some(Evaluation::Win),
+        Evaluation::Loss => Some(Evaluation::Loss),
+        Evaluation::Draw => Some(Evaluation::Draw),
         Evaluation::HeuristicScore(_) => {
             if depth == 0 {
-                return static_evaluation
+                return Some(static_evaluation)
             };
 
             for coordinates in position.generate_moves() {
-                let new_position = position.play(coordinates);
-                let evaluation = -get_negamax_evaluation(&new_position, depth-1, num_positions_traversed_so_far, -beta, -alpha);
+                let mut continuation = Vec::new();
+                position.make_move(coordinates);
+                let evaluation = get_negamax_evaluation(position, depth-1, num_positions_traversed_so_far, interruption, -beta, -alpha, &mut continuation).map(|e| -e);
+                position.unmake_move();
+                let evaluation = evaluation?;
 
                 if evaluation >= beta {
-                    return evaluation
+                    return Some(evaluation)
                 };
 
-                if evaluation >= alpha {
-                    alpha = evaluation
+                if evaluation > alpha {
+                    alpha = evaluation;
+                    principal_variation.clear();
+                    principal_variation.push(coordinates);
+                    principal_variation.append(&mut continuation);
                 };
             }
 
-            alpha
+            Some(alpha)
         }
     }
 }
 
 
+/// Lets a search running on one thread be stopped from another. Clones of a handle share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct StopHandle {
+    stopped: Arc<AtomicBool>
+}
+
+impl StopHandle {
+    pub fn new() -> Self {
+        StopHandle::default()
+    }
+
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+}
+
+
+/// Tells the time for searches with a time budget. Where `std::time::Instant` is not available, as in browsers, a
+/// clock can be made from whatever timer the platform does provide.
+pub trait Clock: Sync {
+    /// Returns the time elapsed since some fixed point, which need not be the same from one clock to another.
+    fn now(&self) -> Duration;
+}
+
+
+/// A clock that uses `std::time::Instant`. WebAssembly in a browser has no such clock, so there neither this nor the
+/// `analyse` functions that rely on it are available, and searches go through `analyse_with_clock` instead.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct SystemClock {
+    origin: Instant
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock::new()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SystemClock {
+    pub fn new() -> Self {
+        SystemClock { origin: Instant::now() }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+
+#[derive(Clone, Copy)]
+struct Deadline<'a> {
+    clock: &'a dyn Clock,
+    time: Duration
+}
+
+
+/// The conditions under which a search in progress gives up.
+#[derive(Clone, Copy)]
+struct Interruption<'a> {
+    deadline: Option<Deadline<'a>>,
+    stop: Option<&'a StopHandle>
+}
+
+impl Interruption<'_> {
+    const NEVER: Interruption<'static> = Interruption { deadline: None, stop: None };
+
+    fn is_due(&self) -> bool {
+        self.stop.is_some_and(|stop| stop.is_stopped()) || self.deadline.is_some_and(|deadline| deadline.clock.now() >= deadline.time)
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: u8,
+    pub time_budget: Option<Duration>,
+    /// How many threads to split the moves from the root between.
+    pub threads: usize
+}
+
+
+#[derive(Debug, Clone)]
 pub struct AnalysisReport {
     pub evaluation: Evaluation,
     pub best_move: Coordinates,
+    /// The line of play the engine expects, starting with its best move.
+    pub principal_variation: Vec<Coordinates>,
+    /// Each move from the root with its evaluation at the report's depth, in move order. Only `analyse_every_move`
+    /// fills this in; the other searches leave it empty.
+    pub move_evaluations: Vec<(Coordinates, Evaluation)>,
+    pub depth: u8,
     pub search_time: Duration,
     pub num_positions_traversed: u32
 }
@@ -83,3 +364,107 @@ struct _TranspositionTableEntry {
     pub evaluation_lower_bound: Evaluation,
     pub search_depth: u8,
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::{analyse, analyse_every_move, analyse_with_clock, analyse_with_limits, analyse_with_progress, Clock, SearchLimits, StopHandle, MAX_DEPTH};
+    use crate::position::{Position, Coordinates, Evaluation, EngineError};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Duration;
+
+    /// A clock that moves on a millisecond every time it is read.
+    struct SteppingClock(AtomicU64);
+
+    impl Clock for SteppingClock {
+        fn now(&self) -> Duration {
+            Duration::from_millis(self.0.fetch_add(1, Ordering::Relaxed))
+        }
+    }
+
+    #[test]
+    fn test_principal_variation() {
+        let mut position = Position::new();
+        for _ in 0..3 {
+            position = position.play(Coordinates::new(0, 0)).play(Coordinates::new(3, 3));
+        }
+
+        let report = analyse(&position, 2).unwrap();
+        assert_eq!(report.evaluation, Evaluation::Win);
+        assert_eq!(report.principal_variation, vec![Coordinates::new(0, 0)]);
+        assert_eq!(analyse(&position.play(Coordinates::new(0, 0)), 2).err(), Some(EngineError::TerminalPosition));
+
+        let position = position.play(Coordinates::new(1, 1));
+        let report = analyse(&position, 3).unwrap();
+        assert_eq!(report.best_move, Coordinates::new(3, 3));
+        assert_eq!(report.principal_variation[0], report.best_move);
+
+        let report = analyse(&Position::new(), 2).unwrap();
+        assert_eq!(report.principal_variation.len(), 3);
+        assert_eq!(report.principal_variation[0], report.best_move);
+
+        let mut replayed = Position::new();
+        for coordinates in &report.principal_variation {
+            assert!(replayed.can_play(*coordinates));
+            replayed.make_move(*coordinates);
+        }
+    }
+
+    #[test]
+    fn test_threads_agree_with_single_threaded_search() {
+        let position = Position::new().play(Coordinates::new(1, 1)).play(Coordinates::new(2, 2));
+        let single = analyse(&position, 3).unwrap();
+        let split = analyse_with_limits(&position, SearchLimits { depth: 3, time_budget: None, threads: 4 }).unwrap();
+
+        assert_eq!(split.evaluation, single.evaluation);
+        assert_eq!(split.best_move, single.best_move);
+        assert_eq!(split.principal_variation, single.principal_variation);
+    }
+
+    #[test]
+    fn test_every_move_scored() {
+        let position = Position::new().play(Coordinates::new(1, 1)).play(Coordinates::new(2, 2));
+        let best = analyse(&position, 2).unwrap();
+        assert!(best.move_evaluations.is_empty());
+
+        let single = analyse_every_move(&position, SearchLimits { depth: 2, time_budget: None, threads: 1 }, &StopHandle::new(), &mut |_| {}).unwrap();
+        let split = analyse_every_move(&position, SearchLimits { depth: 2, time_budget: None, threads: 3 }, &StopHandle::new(), &mut |_| {}).unwrap();
+        assert_eq!(single.move_evaluations.len(), 16);
+        assert_eq!(split.move_evaluations, single.move_evaluations);
+        assert_eq!(single.move_evaluations.iter().map(|(_, evaluation)| *evaluation).max(), Some(best.evaluation));
+        assert!(single.move_evaluations.contains(&(best.best_move, best.evaluation)));
+    }
+
+    #[test]
+    fn test_progress() {
+        let limits = SearchLimits { depth: 4, time_budget: Some(Duration::from_secs(60)), threads: 1 };
+        let mut depths = Vec::new();
+        let report = analyse_with_progress(&Position::new(), limits, &StopHandle::new(), &mut |progress| {
+            assert_eq!(progress.principal_variation[0], progress.best_move);
+            depths.push(progress.depth);
+        }).unwrap();
+
+        assert_eq!(depths, vec![1, 2, 3, 4]);
+        assert_eq!(report.depth, 4);
+
+        let position = Position::new().play(Coordinates::new(1, 1));
+        let limits = SearchLimits { depth: 3, time_budget: None, threads: 1 };
+        let mut depths = Vec::new();
+        let report = analyse_with_progress(&position, limits, &StopHandle::new(), &mut |progress| depths.push(progress.depth)).unwrap();
+        let fixed = analyse(&position, 3).unwrap();
+
+        assert_eq!(depths, vec![1, 2, 3]);
+        assert_eq!((report.evaluation, report.principal_variation), (fixed.evaluation, fixed.principal_variation));
+    }
+
+    #[test]
+    fn test_injected_clock() {
+        let clock = SteppingClock(AtomicU64::new(1000));
+        let limits = SearchLimits { depth: MAX_DEPTH, time_budget: Some(Duration::from_millis(20)), threads: 1 };
+        let report = analyse_with_clock(&Position::new(), limits, &clock, &StopHandle::new(), &mut |_| {}).unwrap();
+
+        // The search can only have run out of time by the stepping clock.
+        assert!(report.depth > 0 && report.depth < MAX_DEPTH);
+        assert!(report.search_time >= Duration::from_millis(20));
+    }
+}
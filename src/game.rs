@@ -0,0 +1,342 @@
+use crate::position::{Position, Coordinates, Evaluation, Player, ParseCoordinatesError, ParsePositionError, EngineError};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+
+/// A record of a game: the current position, the moves that led to it, and any moves that have been taken back and
+/// can be replayed.
+pub struct Game {
+    start_position: Position,
+    position: Position,
+    moves: Vec<MoveRecord>,
+    undone_moves: Vec<MoveRecord>
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
+}
+
+impl Game {
+    pub fn new() -> Self {
+        Game::from_position(Position::new())
+    }
+
+    /// Starts a record of a game from an arbitrary position.
+    pub fn from_position(position: Position) -> Self {
+        Game { start_position: position.clone(), position, moves: Vec::new(), undone_moves: Vec::new() }
+    }
+
+    /// Reads a game from either a position string or a move list, telling them apart by the floor separators that
+    /// only position strings have.
+    pub fn from_position_or_moves(s: &str) -> Result<Game, ParsePositionOrMovesError> {
+        match s.contains('/') {
+            true => s.parse::<Position>().map(Game::from_position).map_err(ParsePositionOrMovesError::Position),
+            false => s.parse::<Game>().map_err(ParsePositionOrMovesError::Moves)
+        }
+    }
+
+    /// Returns the position the game started from.
+    pub fn start_position(&self) -> &Position {
+        &self.start_position
+    }
+
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    pub fn moves(&self) -> &[MoveRecord] {
+        &self.moves
+    }
+
+    /// Replays the first `num_moves` moves from the start position and returns the position they lead to.
+    pub fn position_after(&self, num_moves: usize) -> Position {
+        let mut position = self.start_position.clone();
+        for record in &self.moves[..num_moves] {
+            position.make_move(record.coordinates);
+        }
+        position
+    }
+
+    /// Plays a move and records it, along with the time it was played if the caller knows it. The evaluation, if
+    /// given, should be from the perspective of the player making the move. Any moves that were previously taken back
+    /// can no longer be redone. Returns an error, leaving the game as it was, if the column is full or the game is
+    /// over.
+    pub fn play(&mut self, coordinates: Coordinates, participant: Participant, timestamp: Option<SystemTime>, evaluation: Option<Evaluation>) -> Result<(), EngineError> {
+        self.play_record(MoveRecord {
+            coordinates,
+            player: self.position.active_player,
+            participant,
+            timestamp,
+            evaluation
+        })
+    }
+
+    /// Plays a move from an existing record, such as one read back from a file, keeping its details as they are.
+    /// Returns an error, leaving the game as it was, if the move was recorded for the player who is not to move, the
+    /// column is full or the game is over.
+    pub fn play_record(&mut self, record: MoveRecord) -> Result<(), EngineError> {
+        if record.player != self.position.active_player {
+            return Err(EngineError::OutOfTurn(record.player))
+        };
+
+        self.position.try_make_move(record.coordinates)?;
+        self.moves.push(record);
+        self.undone_moves.clear();
+        Ok(())
+    }
+
+    /// Takes back the last move played, returning its record, or `None` if no moves have been played.
+    pub fn undo(&mut self) -> Option<MoveRecord> {
+        let record = self.moves.pop()?;
+        self.position.unmake_move();
+        self.undone_moves.push(record);
+        Some(record)
+    }
+
+    /// Replays the last move that was taken back, returning its record, or `None` if there is nothing to redo.
+    pub fn redo(&mut self) -> Option<MoveRecord> {
+        let record = self.undone_moves.pop()?;
+        self.position.make_move(record.coordinates);
+        self.moves.push(record);
+        Some(record)
+    }
+
+    /// Returns the result of the game, or `None` if it is still in progress.
+    pub fn result(&self) -> Option<GameResult> {
+        match self.position.get_static_evaluation() {
+            Evaluation::Loss => Some(GameResult::Win(-self.position.active_player)),
+            Evaluation::Draw => Some(GameResult::Draw),
+            _ => None
+        }
+    }
+}
+
+
+/// Formats the game as a move list in notation, such as "a1 d4 a4".
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let notation: Vec<String> = self.moves.iter().map(|record| record.coordinates.to_string()).collect();
+        write!(f, "{}", notation.join(" "))
+    }
+}
+
+/// Parses a whitespace-separated move list such as "a1 d4 a4:2" into a game played from the starting position. Floor
+/// annotations, where given, are checked against where the piece actually lands.
+impl FromStr for Game {
+    type Err = ParseGameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut game = Game::new();
+        for coordinates in parse_moves(s)?.1 {
+            game.play(coordinates, Participant::Unknown, None, None).expect("The moves were checked as they were parsed");
+        }
+        Ok(game)
+    }
+}
+
+
+/// Reads a position from either a position string or a move list, as `Game::from_position_or_moves` does, but keeps
+/// only the position, without recording the moves that led to it.
+pub fn read_position(s: &str) -> Result<Position, ParsePositionOrMovesError> {
+    match s.contains('/') {
+        true => s.parse().map_err(ParsePositionOrMovesError::Position),
+        false => parse_moves(s).map(|(position, _)| position).map_err(ParsePositionOrMovesError::Moves)
+    }
+}
+
+
+/// Parses a move list, checking each move against the position it is played in, and returns the moves along with
+/// the position they lead to from the starting position.
+fn parse_moves(s: &str) -> Result<(Position, Vec<Coordinates>), ParseGameError> {
+    let mut position = Position::new();
+    let mut moves = Vec::new();
+
+    for (i, token) in s.split_whitespace().enumerate() {
+        let error = |kind| ParseGameError { move_number: i+1, kind };
+
+        let (coordinates, floor) = Coordinates::parse_with_floor(token).map_err(|e| error(ParseGameErrorKind::InvalidNotation(e)))?;
+
+        if position.is_terminal() {
+            return Err(error(ParseGameErrorKind::GameAlreadyOver))
+        };
+
+        let Some(landing_cell) = position.landing_cell(coordinates) else {
+            return Err(error(ParseGameErrorKind::ColumnFull(coordinates)))
+        };
+
+        if let Some(floor) = floor {
+            if floor != landing_cell.floor() {
+                return Err(error(ParseGameErrorKind::FloorMismatch { annotated: floor, actual: landing_cell.floor() }))
+            }
+        };
+
+        position.make_move(coordinates);
+        moves.push(coordinates);
+    }
+
+    Ok((position, moves))
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseGameError {
+    pub move_number: usize,
+    pub kind: ParseGameErrorKind
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseGameErrorKind {
+    InvalidNotation(ParseCoordinatesError),
+    ColumnFull(Coordinates),
+    FloorMismatch { annotated: usize, actual: usize },
+    GameAlreadyOver
+}
+
+impl fmt::Display for ParseGameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "move {}: ", self.move_number)?;
+        match &self.kind {
+            ParseGameErrorKind::InvalidNotation(e) => write!(f, "{}", e),
+            ParseGameErrorKind::ColumnFull(coordinates) => write!(f, "column {} is already full", coordinates),
+            ParseGameErrorKind::FloorMismatch { annotated, actual } => write!(f, "annotated floor {} but the piece lands on floor {}", annotated+1, actual+1),
+            ParseGameErrorKind::GameAlreadyOver => write!(f, "the game is already over")
+        }
+    }
+}
+
+impl Error for ParseGameError {}
+
+
+/// Why `Game::from_position_or_moves` could not read its input, depending on which of the two it was taken to be.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParsePositionOrMovesError {
+    Position(ParsePositionError),
+    Moves(ParseGameError)
+}
+
+impl fmt::Display for ParsePositionOrMovesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParsePositionOrMovesError::Position(e) => write!(f, "{}", e),
+            ParsePositionOrMovesError::Moves(e) => write!(f, "{}", e)
+        }
+    }
+}
+
+impl Error for ParsePositionOrMovesError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParsePositionOrMovesError::Position(e) => Some(e),
+            ParsePositionOrMovesError::Moves(e) => Some(e)
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy)]
+pub struct MoveRecord {
+    pub coordinates: Coordinates,
+    pub player: Player,
+    pub participant: Participant,
+    /// When the move was played, or `None` if that is not known, as for moves read from a move list.
+    pub timestamp: Option<SystemTime>,
+    pub evaluation: Option<Evaluation>
+}
+
+
+/// Who was at the controls for a move. Moves imported from a bare move list are `Unknown`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Participant {
+    Human,
+    Engine,
+    Unknown
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    Win(Player),
+    Draw
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{read_position, Game, GameResult, Participant, ParseGameError, ParseGameErrorKind, ParsePositionOrMovesError};
+    use crate::position::{Position, Coordinates, Evaluation, Player, EngineError, ParsePositionError};
+
+    #[test]
+    fn test_undo_and_redo() {
+        let mut game = Game::new();
+
+        game.play(Coordinates::new(0, 0), Participant::Human, None, None).unwrap();
+        game.play(Coordinates::new(0, 0), Participant::Engine, None, Some(Evaluation::HeuristicScore(3))).unwrap();
+        game.play(Coordinates::new(2, 1), Participant::Human, None, None).unwrap();
+
+        assert_eq!(game.undo().map(|r| r.coordinates), Some(Coordinates::new(2, 1)));
+        assert_eq!(game.undo().map(|r| r.coordinates), Some(Coordinates::new(0, 0)));
+        assert_eq!(game.moves().len(), 1);
+        assert_eq!(game.position().active_player, Player::Black);
+        assert_eq!(game.position().get_static_evaluation(), Evaluation::HeuristicScore(-7));
+
+        let redone = game.redo().unwrap();
+        assert_eq!(redone.coordinates, Coordinates::new(0, 0));
+        assert_eq!(redone.player, Player::Black);
+        assert_eq!(redone.participant, Participant::Engine);
+        assert_eq!(redone.evaluation, Some(Evaluation::HeuristicScore(3)));
+        assert_eq!(game.moves().len(), 2);
+
+        game.play(Coordinates::new(3, 3), Participant::Human, None, None).unwrap();
+        assert!(game.redo().is_none());
+
+        while game.undo().is_some() {}
+        assert_eq!(game.position().get_static_evaluation(), Evaluation::HeuristicScore(0));
+        assert_eq!(game.position().active_player, Player::White);
+    }
+
+    #[test]
+    fn test_result() {
+        let mut game = Game::new();
+
+        for _ in 0..3 {
+            game.play(Coordinates::new(0, 0), Participant::Human, None, None).unwrap();
+            game.play(Coordinates::new(1, 0), Participant::Human, None, None).unwrap();
+        }
+        assert_eq!(game.result(), None);
+
+        let record = game.moves()[1];
+        assert_eq!(game.play_record(record), Err(EngineError::OutOfTurn(Player::Black)));
+
+        game.play(Coordinates::new(0, 0), Participant::Human, None, None).unwrap();
+        assert_eq!(game.result(), Some(GameResult::Win(Player::White)));
+        assert_eq!(game.play_record(record), Err(EngineError::TerminalPosition));
+        assert_eq!(game.moves().len(), 7);
+        assert_eq!(game.position_after(game.moves().len()), *game.position());
+        assert_eq!(game.position_after(2), Position::new().play(Coordinates::new(0, 0)).play(Coordinates::new(1, 0)));
+    }
+
+    #[test]
+    fn test_move_list() {
+        let game: Game = "a1 d4 A1:2 b2".parse().unwrap();
+        assert_eq!(game.moves().len(), 4);
+        assert_eq!(game.moves()[2].player, Player::White);
+        assert_eq!(game.to_string(), "a1 d4 a1 b2");
+
+        assert_eq!("a1 a1:1".parse::<Game>().err(), Some(ParseGameError { move_number: 2, kind: ParseGameErrorKind::FloorMismatch { annotated: 0, actual: 1 } }));
+        assert_eq!("a1 a1 a1 a1 a1".parse::<Game>().err(), Some(ParseGameError { move_number: 5, kind: ParseGameErrorKind::ColumnFull(Coordinates::new(0, 0)) }));
+        assert_eq!("a1 b1 a1 b1 a1 b1 a1 b1".parse::<Game>().err(), Some(ParseGameError { move_number: 8, kind: ParseGameErrorKind::GameAlreadyOver }));
+        assert!(matches!("a1 z9".parse::<Game>(), Err(ParseGameError { move_number: 2, kind: ParseGameErrorKind::InvalidNotation(_) })));
+
+        assert_eq!(Game::from_position_or_moves("a1 d4").map(|game| game.moves().len()), Ok(2));
+        assert!(matches!(Game::from_position_or_moves("a1 a1:1"), Err(ParsePositionOrMovesError::Moves(_))));
+        assert_eq!(Game::from_position_or_moves("w.../ w 1").err(), Some(ParsePositionOrMovesError::Position(ParsePositionError::WrongNumberOfFloors(2))));
+
+        assert_eq!(read_position("a1 d4 A1:2 b2"), Ok(game.position().clone()));
+        assert_eq!(read_position(&game.position().to_string()), Ok(game.position().clone()));
+        assert_eq!(read_position("a1 a1:1").err(), Game::from_position_or_moves("a1 a1:1").err());
+    }
+}
@@ -0,0 +1,275 @@
+use crate::evaluation::AnalysisReport;
+use std::error::Error;
+use std::fmt;
+
+
+/// A parsed JSON value. Only as much of JSON as the engine's interfaces need is supported well: numbers are read as
+/// `f64`, and objects keep their members in order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>)
+}
+
+impl Value {
+    /// Returns the member of an object with the given key, or `None` if there is none or this is not an object.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            _ => None
+        }
+    }
+}
+
+
+/// How deeply arrays and objects may be nested, so that parsing hostile input cannot run out of stack.
+const MAX_NESTING: usize = 64;
+
+
+pub fn parse(s: &str) -> Result<Value, ParseJsonError> {
+    let mut parser = Parser { chars: s.char_indices().peekable(), length: s.len(), nesting: 0 };
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    match parser.chars.next() {
+        None => Ok(value),
+        Some((position, _)) => Err(ParseJsonError { position })
+    }
+}
+
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    length: usize,
+    nesting: usize
+}
+
+impl Parser<'_> {
+    fn error(&mut self) -> ParseJsonError {
+        let position = self.chars.peek().map(|(position, _)| *position).unwrap_or(self.length);
+        ParseJsonError { position }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_ascii_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseJsonError> {
+        match self.chars.next_if(|(_, c)| *c == expected) {
+            Some(_) => Ok(()),
+            None => Err(self.error())
+        }
+    }
+
+    fn expect_word(&mut self, word: &str, value: Value) -> Result<Value, ParseJsonError> {
+        for c in word.chars() {
+            self.expect(c)?;
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<Value, ParseJsonError> {
+        self.skip_whitespace();
+        match self.chars.peek().map(|(_, c)| *c) {
+            Some('n') => self.expect_word("null", Value::Null),
+            Some('t') => self.expect_word("true", Value::Bool(true)),
+            Some('f') => self.expect_word("false", Value::Bool(false)),
+            Some('"') => self.parse_string().map(Value::String),
+            Some('[') => self.parse_nested(Self::parse_array),
+            Some('{') => self.parse_nested(Self::parse_object),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.parse_number(),
+            _ => Err(self.error())
+        }
+    }
+
+    fn parse_nested(&mut self, parse: fn(&mut Self) -> Result<Value, ParseJsonError>) -> Result<Value, ParseJsonError> {
+        if self.nesting == MAX_NESTING {
+            return Err(self.error())
+        };
+        self.nesting += 1;
+        let value = parse(self);
+        self.nesting -= 1;
+        value
+    }
+
+    fn parse_number(&mut self) -> Result<Value, ParseJsonError> {
+        let start = self.error();
+        let mut number = String::new();
+        while let Some((_, c)) = self.chars.next_if(|(_, c)| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+            number.push(c);
+        }
+        number.parse().map(Value::Number).map_err(|_| start)
+    }
+
+    fn parse_string(&mut self) -> Result<String, ParseJsonError> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            let error = self.error();
+            match self.chars.next().ok_or(error)?.1 {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escaped = match self.chars.next().ok_or(error)?.1 {
+                        '"' => '"',
+                        '\\' => '\\',
+                        '/' => '/',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => {
+                            let hex: String = (0..4).filter_map(|_| self.chars.next().map(|(_, c)| c)).collect();
+                            u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32).ok_or(error)?
+                        },
+                        _ => return Err(error)
+                    };
+                    s.push(escaped);
+                },
+                c => s.push(c)
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Value, ParseJsonError> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.chars.next_if(|(_, c)| *c == ']').is_some() {
+            return Ok(Value::Array(values))
+        };
+
+        loop {
+            values.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, ']')) => return Ok(Value::Array(values)),
+                _ => return Err(self.error())
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Value, ParseJsonError> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.chars.next_if(|(_, c)| *c == '}').is_some() {
+            return Ok(Value::Object(members))
+        };
+
+        loop {
+            self.skip_whitespace();
+            let name = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            members.push((name, self.parse_value()?));
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, '}')) => return Ok(Value::Object(members)),
+                _ => return Err(self.error())
+            }
+        }
+    }
+}
+
+
+/// Formats a string as a JSON string literal, quotes included.
+pub fn string(s: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c)
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+
+/// Formats the fields of an analysis report as the members of a JSON object, without the surrounding braces, so
+/// that other members can be added alongside them.
+pub fn report_members(report: &AnalysisReport) -> String {
+    let principal_variation: Vec<String> = report.principal_variation.iter().map(|coordinates| string(&coordinates.to_string())).collect();
+
+    format!(
+        "\"best_move\": \"{}\", \"evaluation\": \"{}\", \"confidence\": {:.3}, \"principal_variation\": [{}], \"depth\": {}, \"nodes\": {}, \"time_ms\": {}",
+        report.best_move,
+        report.evaluation,
+        report.evaluation.to_confidence(),
+        principal_variation.join(", "),
+        report.depth,
+        report.num_positions_traversed,
+        report.search_time.as_millis()
+    )
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseJsonError {
+    /// The byte offset at which the input stopped making sense.
+    pub position: usize
+}
+
+impl fmt::Display for ParseJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid JSON at byte {}", self.position)
+    }
+}
+
+impl Error for ParseJsonError {}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, string, ParseJsonError, Value, MAX_NESTING};
+
+    #[test]
+    fn test_parse() {
+        let value = parse(r#" {"moves": "a1 d4", "depth": 6, "flags": [true, null], "note": "\"hi\"\né"} "#).unwrap();
+        assert_eq!(value.get("moves").and_then(Value::as_str), Some("a1 d4"));
+        assert_eq!(value.get("depth").and_then(Value::as_f64), Some(6.0));
+        assert_eq!(value.get("flags"), Some(&Value::Array(vec![Value::Bool(true), Value::Null])));
+        assert_eq!(value.get("note").and_then(Value::as_str), Some("\"hi\"\né"));
+        assert_eq!(value.get("missing"), None);
+
+        assert_eq!(parse("{\"a\": 1,}"), Err(ParseJsonError { position: 8 }));
+        assert_eq!(parse("[1, 2"), Err(ParseJsonError { position: 5 }));
+        assert_eq!(parse("{} x"), Err(ParseJsonError { position: 3 }));
+    }
+
+    #[test]
+    fn test_nesting() {
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_NESTING)).is_ok());
+        assert_eq!(parse(&nested(MAX_NESTING+1)), Err(ParseJsonError { position: MAX_NESTING }));
+        assert_eq!(parse(&"[".repeat(60_000)), Err(ParseJsonError { position: MAX_NESTING }));
+    }
+
+    #[test]
+    fn test_string() {
+        assert_eq!(string("a \"b\"\\\n"), r#""a \"b\"\\\n""#);
+    }
+}
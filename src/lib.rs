@@ -0,0 +1,28 @@
+//! The yonmokun engine for 3D connect four, played on a 4x4x4 board.
+//!
+//! A [`Position`] holds the board and the player to move, and the moves that can be played in it are the
+//! [`Coordinates`] of the columns that are not yet full. [`analyse`] searches a position for the best move, and the
+//! other search functions in [`evaluation`] add time limits, threads, cancellation and progress reports. The
+//! interactive game is the `yonmokun` binary built on top of this library.
+//!
+//! Built for WebAssembly, the library leaves out everything that reads the system clock, which browsers do not have:
+//! searches go through [`evaluation::analyse_with_clock`] with a clock the host provides, and a [`game::Game`] only
+//! records when its moves were played if the caller passes in the time.
+
+pub mod position;
+pub mod evaluation;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod difficulty;
+pub mod game;
+pub mod record;
+pub mod json;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ponder;
+#[cfg(feature = "wasm")]
+pub mod web;
+
+
+pub use position::{Position, Coordinates, Cell, Line, LineState, Player, Evaluation, EngineError};
+pub use evaluation::{AnalysisReport, SearchLimits};
+#[cfg(not(target_arch = "wasm32"))]
+pub use evaluation::analyse;
@@ -1,85 +1,646 @@
-mod position;
-mod evaluation;
+mod setup;
+mod command;
+mod messages;
+mod options;
+mod render;
+mod review;
+mod batch;
+mod protocol;
+mod server;
 
 
-use position::{Position, Evaluation, Coordinates};
-use std::io::{self, Write};
+use command::{Command, ParseCommandError};
+use setup::{Controller, Setup};
+use messages::{Locale, Messages};
+use options::{ControllerOption, Mode, Options};
+use yonmokun::position::{Position, Player, Cell, ParseCoordinatesError};
+use yonmokun::game::{Game, GameResult, Participant};
+use yonmokun::difficulty::{self, Difficulty, Rng, MAX_LEVEL};
+use yonmokun::evaluation::{self, AnalysisReport, SearchLimits, StopHandle};
+use yonmokun::ponder::Ponderer;
+use render::RenderOptions;
+use yonmokun::record;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufReader, IsTerminal, Write};
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::Path;
+use std::process;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+
+const DEFAULT_SAVE_FILE: &str = "yonmokun.txt";
+const REVIEW_DEPTH: u8 = 4;
+const HINT_LIMITS: SearchLimits = SearchLimits { depth: 8, time_budget: Some(Duration::from_secs(2)), threads: 1 };
+const AUTO_PLAY_DELAY: Duration = Duration::from_millis(800);
 
 
 fn main() {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("yonmokun: {}\n\n{}", e, options::USAGE);
+            process::exit(2)
+        }
+    };
+
+    if options.show_usage {
+        println!("{}", options::USAGE);
+        return
+    };
+
+    match options.mode {
+        Mode::Play => {},
+        Mode::Analyse => process::exit(run_batch_analysis(&options)),
+        Mode::Engine => {
+            if let Err(e) = protocol::run(io::stdin().lock(), io::stdout(), analysis_limits(&options)) {
+                eprintln!("yonmokun: {}", e);
+                process::exit(2)
+            };
+            return
+        },
+        Mode::Serve => process::exit(run_server(&options))
+    };
+
+    let messages = Messages::new(options.locale.unwrap_or_else(Locale::from_env));
+
     clear_screen();
     println!("      ////^\\\\\\\\\n      | ^   ^ |\n     @ (o) (o) @\n      |   <   |\n      |  ___  |\n       \\_____/\n     ____|  |____\n    /    \\__/    \\\n   /              \\\n  /\\_/|        |\\_/\\   __   _____  _   _ __  __  ___  _  ___   _ _   _\n / /  |        |  \\ \\  \\ \\ / / _ \\| \\ | |  \\/  |/ _ \\| |/ / | | | \\ | |\n( <   |        |   > )  \\ V / | | |  \\| | |\\/| | | | | ' /| | | |  \\| |\n \\ \\  |        |  / /    | || |_| | |\\  | |  | | |_| | . \\| |_| | |\\  |\n  \\ \\ |________| / /     |_| \\___/|_| \\_|_|  |_|\\___/|_|\\_\\\\___/|_| \\_|\n");
-    println!("やあ、僕の名はよんも君。一緒に三次元四目並べを遊ぼう！\n");
+    println!("{}", messages.greeting());
+
+    let (mut setup, mut game): (Setup, Game) = if options.has_players() {
+        get_preset_setup(&messages, &options)
+    } else {
+        get_menu_setup(&messages, &options)
+    };
 
-    let depth: u8;
+    let mut rng = Rng::from_time();
+    let mut view = RenderOptions {
+        flipped: false,
+        colour: !options.no_colour && env::var_os("NO_COLOR").is_none() && io::stdout().is_terminal(),
+        compact: options.compact
+    };
+    let mut ponderer: Option<Ponderer> = None;
+
+    clear_screen();
+    print!("{}", render::render(game.position(), view, &[]));
     loop {
-        println!("1. 本気モード");
-        println!("2. 優しいモード");
-        print!("遊ぶモードを選んでね。[1,2]: ");
+        let active_player = game.position().active_player;
+
+        match setup.controller(active_player) {
+            Controller::Human => {
+                if setup.num_humans() == 1 {
+                    println!("{}", messages.your_turn());
+                } else {
+                    println!("{}", messages.player_turn(stone(active_player)));
+                }
+
+                if setup.show_hints {
+                    print_hint(&messages, &options, game.position());
+                }
+
+                let player_coordinates = match get_command(&messages) {
+                    Command::Play(coordinates, floor) => {
+                        let Some(landing_cell) = game.position().landing_cell(coordinates) else {
+                            println!("{}", messages.cannot_play_there());
+                            continue;
+                        };
+                        if floor.is_some_and(|floor| floor != landing_cell.floor()) {
+                            println!("{}", messages.lands_on_floor(coordinates, landing_cell.floor()+1));
+                            continue;
+                        };
+                        coordinates
+                    },
+                    Command::Undo => {
+                        let last_human_move = match game.moves().iter().rposition(|record| setup.controller(record.player) == Controller::Human) {
+                            Some(i) => i,
+                            None => {
+                                println!("{}", messages.nothing_to_undo());
+                                continue;
+                            }
+                        };
+                        while game.moves().len() > last_human_move {
+                            game.undo();
+                        }
+                        clear_screen();
+                        print!("{}", render::render(game.position(), view, &[]));
+                        println!("{}", messages.undone());
+                        continue;
+                    },
+                    Command::Redo => {
+                        if game.redo().is_none() {
+                            println!("{}", messages.nothing_to_redo());
+                            continue;
+                        };
+                        while setup.controller(game.position().active_player) != Controller::Human && game.redo().is_some() {}
+                        clear_screen();
+                        print!("{}", render::render(game.position(), view, &[]));
+                        println!("{}", messages.redone());
+                        continue;
+                    },
+                    Command::Hint => {
+                        print_hint(&messages, &options, game.position());
+                        continue;
+                    },
+                    Command::Save(path) => {
+                        let path = path.unwrap_or_else(|| DEFAULT_SAVE_FILE.to_string());
+                        match record::save(Path::new(&path), &game, &setup.to_settings()) {
+                            Ok(()) => println!("{}", messages.saved(&path)),
+                            Err(e) => println!("{}", messages.save_failed(e))
+                        }
+                        continue;
+                    },
+                    Command::Load(path) => {
+                        let path = path.unwrap_or_else(|| DEFAULT_SAVE_FILE.to_string());
+                        if let Some((loaded_setup, loaded_game)) = load_saved_game(&messages, &path) {
+                            (setup, game) = (loaded_setup, loaded_game);
+                            clear_screen();
+                            print!("{}", render::render(game.position(), view, &[]));
+                            println!("{}", messages.loaded(&path));
+                        };
+                        continue;
+                    },
+                    Command::Quit => break,
+                    Command::Help => {
+                        print_help(&messages);
+                        continue;
+                    },
+                    Command::FlipView => {
+                        view.flipped = !view.flipped;
+                        clear_screen();
+                        print!("{}", render::render(game.position(), view, &[]));
+                        continue;
+                    }
+                };
+                game.play(player_coordinates, Participant::Human, Some(SystemTime::now()), None).expect("The move was checked against the position above");
+            },
+            Controller::Engine { level } => {
+                if setup.num_humans() == 0 {
+                    if setup.auto_play {
+                        thread::sleep(AUTO_PLAY_DELAY);
+                    } else {
+                        print!("{}", messages.press_enter());
+                        io::stdout().flush().unwrap();
+                        io::stdin().read_line(&mut String::new()).expect("Failed to read line");
+                    }
+                }
+
+                let mut difficulty = Difficulty::level(level).unwrap();
+                difficulty.limits = options.search_limits(difficulty.limits);
+                let pondered_report = match difficulty.plays_best_move() {
+                    true => ponderer.take().and_then(|ponderer| ponderer.finish(game.position(), difficulty.limits)),
+                    false => None
+                };
+                let report = match pondered_report {
+                    Some(report) => report,
+                    None => difficulty::choose_move(game.position(), &difficulty, &mut rng, &mut |progress| print_progress(&messages, progress))
+                        .expect("The engine only moves while the game is in progress")
+                };
+                game.play(report.best_move, Participant::Engine, Some(SystemTime::now()), Some(report.evaluation)).expect("The engine only plays legal moves");
+
+                clear_screen();
+                print!("{}", render::render(game.position(), view, &[]));
+                if setup.num_humans() == 1 {
+                    println!("{}", messages.engine_played(report.best_move));
+                } else {
+                    println!("{}", messages.player_played(stone(active_player), report.best_move));
+                }
+                println!("{}", messages.confidence(report.evaluation.to_confidence() * 100.0));
+                println!("{}", messages.search_depth(report.depth));
+                println!("{}", messages.computation_time(report.search_time.as_millis()));
+                println!("{}", messages.positions_traversed(report.num_positions_traversed));
+
+                let is_human_next = setup.controller(game.position().active_player) == Controller::Human;
+                if options.ponder && difficulty.plays_best_move() && is_human_next {
+                    ponderer = report.principal_variation.get(1).and_then(|&expected_reply| Ponderer::start(game.position(), expected_reply, difficulty.limits));
+                };
+            }
+        }
+
+        if game.position().is_terminal() {
+            break;
+        };
+    }
+
+    if game.result().is_none() {
+        println!("{}", messages.goodbye());
+        return
+    };
+
+    let winning_lines = game.position().winning_lines();
+    if !winning_lines.is_empty() {
+        // Drawn below the last board rather than over it, so that the report of the last move stays on screen.
+        let winning_cells: Vec<Cell> = winning_lines.iter().flat_map(|line| line.cells).collect();
+        print!("{}", render::render(game.position(), view, &winning_cells));
+        for line in &winning_lines {
+            let cells: Vec<String> = line.cells.iter().map(|cell| cell.to_string()).collect();
+            println!("{}", messages.winning_line(&cells.join(" ")));
+        }
+        println!();
+    };
+
+    print_game_record(&messages, &game);
+
+    match game.result() {
+        Some(GameResult::Win(winner)) => {
+            if setup.num_humans() != 1 {
+                println!("{}", messages.player_wins(stone(winner)));
+            } else if setup.controller(winner) == Controller::Human {
+                println!("{}", messages.human_wins());
+            } else {
+                println!("{}", messages.engine_wins());
+            }
+        },
+        Some(GameResult::Draw) => println!("{}", messages.draw()),
+        None => panic!("Something went wrong.")
+    }
+
+    if get_yes_or_no(&messages, messages.ask_review()) {
+        print_game_review(&messages, &game);
+    }
+}
+
+
+/// Re-analyses each move of the game, showing how the evaluation swung and pointing out blunders along with the
+/// move that should have been played instead.
+fn print_game_review(messages: &Messages, game: &Game) {
+    println!("{}", messages.reviewing());
+
+    // Only a game saved by hand could have moves played after it was over.
+    let Ok(reviews) = review::review_game(game, REVIEW_DEPTH) else {
+        return
+    };
+    for (i, review) in reviews.iter().enumerate() {
+        let best_confidence = review.best_evaluation.to_confidence() * 100.0;
+        let played_confidence = review.played_evaluation.to_confidence() * 100.0;
+        print!("{:>2}. {} {}  {}", i+1, stone(review.record.player), review.record.coordinates, messages.confidence_swing(best_confidence, played_confidence));
+
+        if review.is_blunder {
+            println!("  {}", messages.blunder(review.best_move));
+        } else {
+            println!();
+        }
+    }
+
+    let num_blunders = reviews.iter().filter(|review| review.is_blunder).count();
+    println!("{}", messages.num_blunders(num_blunders));
+}
+
+
+/// Shows the move the engine would play in the position, without playing it.
+fn print_hint(messages: &Messages, options: &Options, position: &Position) {
+    let limits = SearchLimits { threads: options.threads.unwrap_or(HINT_LIMITS.threads), ..HINT_LIMITS };
+    let Ok(report) = evaluation::analyse_with_progress(position, limits, &StopHandle::new(), &mut |progress| print_progress(messages, progress)) else {
+        return
+    };
+    print!("\r\x1b[K");
+    let principal_variation: Vec<String> = report.principal_variation.iter().map(|coordinates| coordinates.to_string()).collect();
+
+    println!("{}", messages.hint(report.best_move));
+    println!("{}", messages.confidence(report.evaluation.to_confidence() * 100.0));
+    println!("{}", messages.principal_variation(&principal_variation.join(" ")));
+}
+
+
+/// Shows how far the engine has got with a search on a line that is overwritten as the search goes deeper.
+fn print_progress(messages: &Messages, progress: &AnalysisReport) {
+    print!("\r\x1b[K{}", messages.thinking(progress.depth, progress.best_move, progress.evaluation.to_confidence() * 100.0));
+    io::stdout().flush().unwrap();
+}
+
+
+fn stone(player: Player) -> &'static str {
+    match player {
+        Player::White => "○",
+        Player::Black => "●"
+    }
+}
+
+
+/// The limits for searches outside of a game: those of the level if one was given, or otherwise the defaults for
+/// batch analysis, adjusted by the other search options.
+fn analysis_limits(options: &Options) -> SearchLimits {
+    options.search_limits(match options.level {
+        Some(level) => Difficulty::level(level).unwrap().limits,
+        None => batch::DEFAULT_LIMITS
+    })
+}
+
+
+/// Runs `yonmokun analyse`, returning the exit code: 1 if any of the positions could not be analysed, or 2 if the
+/// input could not be read at all.
+fn run_batch_analysis(options: &Options) -> i32 {
+    let limits = analysis_limits(options);
+
+    let mut writer = io::stdout().lock();
+    let result = match &options.input {
+        Some(path) => File::open(path).and_then(|file| batch::analyse_all(BufReader::new(file), &mut writer, limits, options.format)),
+        None => batch::analyse_all(io::stdin().lock(), &mut writer, limits, options.format)
+    };
+
+    match result {
+        Ok(0) => 0,
+        Ok(_) => 1,
+        Err(e) => {
+            eprintln!("yonmokun: {}", e);
+            2
+        }
+    }
+}
+
+
+/// Runs `yonmokun serve` until it fails, returning the exit code.
+fn run_server(options: &Options) -> i32 {
+    let port = options.port.unwrap_or(server::DEFAULT_PORT);
+    let result = TcpListener::bind((Ipv4Addr::LOCALHOST, port)).and_then(|listener| {
+        eprintln!("yonmokun: listening on http://{}", listener.local_addr()?);
+        server::serve(listener, analysis_limits(options))
+    });
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("yonmokun: {}", e);
+            2
+        }
+    }
+}
+
+
+/// Asks which mode to play in and sets up the game for it.
+fn get_menu_setup(messages: &Messages, options: &Options) -> (Setup, Game) {
+    loop {
+        println!("1. {}", messages.menu_play_engine());
+        println!("2. {}", messages.menu_hot_seat());
+        println!("3. {}", messages.menu_engine_vs_engine());
+        println!("4. {}", messages.menu_load());
+        print!("{}", messages.choose_mode(4));
         io::stdout().flush().unwrap();
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).expect("Failed to read line");
-        input = input.trim().to_string();
+        let input = read_answer();
 
-        match &input[..] {
-            "1" => depth = 6,
-            "2" => depth = 1,
+        match input.trim() {
+            "1" => break get_engine_game_setup(messages, options, get_engine_level(messages, options, None)),
+            "2" => {
+                let show_hints = options.show_hints || get_yes_or_no(messages, messages.ask_hints());
+                let setup = Setup { white: Controller::Human, black: Controller::Human, show_hints, auto_play: false };
+                break (setup, get_starting_game(messages, options))
+            },
+            "3" => {
+                let white = Controller::Engine { level: get_engine_level(messages, options, Some(Player::White)) };
+                let black = Controller::Engine { level: get_engine_level(messages, options, Some(Player::Black)) };
+                let auto_play = options.auto_play || get_yes_or_no(messages, messages.ask_auto_play());
+                let setup = Setup { white, black, show_hints: false, auto_play };
+                break (setup, get_starting_game(messages, options))
+            },
+            "4" => match load_saved_game(messages, &get_file_name(messages)) {
+                Some(loaded) => break loaded,
+                None => continue
+            },
             _ => {
-                println!("１か２を選んでね。");
+                println!("{}", messages.choose_between(4));
                 continue;
             }
         }
+    }
+}
+
+
+/// Sets up the game given by the command-line options, asking only for what they leave out.
+fn get_preset_setup(messages: &Messages, options: &Options) -> (Setup, Game) {
+    let is_engine = |option| matches!(option, Some(ControllerOption::Engine { .. }));
+    let both_engines = is_engine(options.white) && is_engine(options.black);
+    let controller = |option: Option<ControllerOption>, player| match option.unwrap_or(ControllerOption::Human) {
+        ControllerOption::Human => Controller::Human,
+        ControllerOption::Engine { level: Some(level) } => Controller::Engine { level },
+        ControllerOption::Engine { level: None } => Controller::Engine { level: get_engine_level(messages, options, both_engines.then_some(player)) }
+    };
+
+    let setup = Setup {
+        white: controller(options.white, Player::White),
+        black: controller(options.black, Player::Black),
+        show_hints: options.show_hints,
+        auto_play: options.auto_play
+    };
 
-        break;
+    let game = get_starting_game(messages, options);
+    match options.first_player {
+        Some(first_player) if is_new_game(&game) => (setup, Game::from_position(Position::starting_with(first_player))),
+        _ => (setup, game)
     }
+}
+
 
+/// Sets up a game between the user and the engine playing at the given level, asking the user which side they want
+/// to play.
+fn get_engine_game_setup(messages: &Messages, options: &Options, level: u8) -> (Setup, Game) {
+    let engine = Controller::Engine { level };
+    let setup_for = |human_player| match human_player {
+        Player::White => Setup { white: Controller::Human, black: engine, show_hints: false, auto_play: false },
+        Player::Black => Setup { white: engine, black: Controller::Human, show_hints: false, auto_play: false }
+    };
 
-    let mut current_position = Position::new();
+    let game = get_starting_game(messages, options);
+    if is_new_game(&game) {
+        let (first_player, human_player) = match options.first_player {
+            Some(first_player) => (first_player, get_user_colour(messages)),
+            None => get_user_sides(messages)
+        };
+        (setup_for(human_player), Game::from_position(Position::starting_with(first_player)))
+    } else {
+        (setup_for(get_user_colour(messages)), game)
+    }
+}
 
-    let final_evaluation: Evaluation;
-    let last_move_by_player: bool;
 
-    current_position.print();
+fn is_new_game(game: &Game) -> bool {
+    game.moves().is_empty() && *game.start_position() == Position::new()
+}
+
+
+/// Asks for the level the engine should play at, naming which side it plays if given, unless it was given on the
+/// command line.
+fn get_engine_level(messages: &Messages, options: &Options, player: Option<Player>) -> u8 {
+    if let Some(level) = options.level {
+        return level
+    };
+
     loop {
-        println!("君の番だよ。");
+        for level in 1..=MAX_LEVEL {
+            println!("{}. {}", level, messages.level_name(level));
+        }
+        print!("{}", messages.choose_level(player.map(stone), MAX_LEVEL));
+        io::stdout().flush().unwrap();
 
-        let player_coordinates = get_user_coordinates(&current_position);
-        current_position = current_position.play(player_coordinates);
+        let input = read_answer();
 
-        if current_position.is_terminal() {
-            final_evaluation = current_position.get_static_evaluation();
-            last_move_by_player = true;
-            break;
-        };
+        match input.trim().parse::<u8>() {
+            Ok(level) if (1..=MAX_LEVEL).contains(&level) => return level,
+            _ => println!("{}", messages.choose_between(MAX_LEVEL as usize))
+        }
+    }
+}
 
-        let report = evaluation::analyse(&current_position, depth);
-        current_position = current_position.play(report.best_move);
 
-        clear_screen();
-        current_position.print();
-        println!("僕はここに打ったよ。{}", report.best_move);
-        println!("自信係数: {:.0}%", report.evaluation.to_confidence() * 100.0);
-        println!("computation time (ms):   {}", report.search_time.as_millis());
-        println!("num positions traversed: {}", report.num_positions_traversed);
+/// Reads the answer to a setup question, leaving the program once the input has ended, as nobody is left to answer.
+fn read_answer() -> String {
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input).expect("Failed to read line") == 0 {
+        println!();
+        process::exit(0)
+    };
+    input
+}
 
-        if current_position.is_terminal() {
-            final_evaluation = current_position.get_static_evaluation();
-            last_move_by_player = false;
-            break;
+
+fn get_yes_or_no(messages: &Messages, question: &str) -> bool {
+    loop {
+        print!("{}", messages.yes_or_no(question));
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input).expect("Failed to read line") == 0 {
+            return false
         };
+
+        match input.trim() {
+            "1" => return true,
+            "2" => return false,
+            _ => println!("{}", messages.choose_between(2))
+        }
     }
+}
 
-    match final_evaluation {
-        Evaluation::Loss => {
-            if last_move_by_player {
-                println!("負けました！楽しい対局をありがとう。");
-            } else {
-                println!("やった、僕の勝ち！楽しい対局をありがとう。");
-            }
+
+/// Asks whether the user wants to move first or second and which colour they want to play, returning the player who
+/// moves first and the player the user controls.
+fn get_user_sides(messages: &Messages) -> (Player, Player) {
+    let user_moves_first = loop {
+        println!("1. {}", messages.first_player_option());
+        println!("2. {}", messages.second_player_option());
+        print!("{}", messages.ask_first_or_second());
+        io::stdout().flush().unwrap();
+
+        let input = read_answer();
+
+        match input.trim() {
+            "1" => break true,
+            "2" => break false,
+            _ => println!("{}", messages.choose_between(2))
+        }
+    };
+
+    let human_player = get_user_colour(messages);
+    let first_player = if user_moves_first { human_player } else { -human_player };
+
+    (first_player, human_player)
+}
+
+
+fn get_user_colour(messages: &Messages) -> Player {
+    loop {
+        print!("{}", messages.ask_colour());
+        io::stdout().flush().unwrap();
+
+        let input = read_answer();
+
+        match input.trim() {
+            "1" => return Player::White,
+            "2" => return Player::Black,
+            _ => println!("{}", messages.choose_between(2))
+        }
+    }
+}
+
+
+fn print_game_record(messages: &Messages, game: &Game) {
+    println!("{}", messages.game_record());
+    for (i, record) in game.moves().iter().enumerate() {
+        let stone = stone(record.player);
+        let participant = messages.participant(record.participant);
+        match record.evaluation {
+            Some(evaluation) => println!("{:>2}. {} {} {} ({})", i+1, stone, record.coordinates, participant, messages.confidence(evaluation.to_confidence() * 100.0)),
+            None => println!("{:>2}. {} {} {}", i+1, stone, record.coordinates, participant)
+        }
+    }
+
+    let mut timestamps = game.moves().iter().filter_map(|record| record.timestamp);
+    if let Some(first) = timestamps.next() {
+        let duration = timestamps.next_back().unwrap_or(first).duration_since(first).unwrap_or_default();
+        println!("{}", messages.game_duration(duration.as_secs() / 60, duration.as_secs() % 60));
+    }
+    println!("{}", messages.move_list(game));
+    println!("{}\n", messages.final_position(game.position()));
+}
+
+
+/// Asks for a move list or position string to start from, unless one was given on the command line, in which case
+/// it had better be valid.
+fn get_starting_game(messages: &Messages, options: &Options) -> Game {
+    if let Some(start) = &options.start {
+        match Game::from_position_or_moves(start) {
+            Ok(game) if game.result().is_none() => return game,
+            Ok(_) => eprintln!("{}", messages.game_already_over()),
+            Err(e) => eprintln!("{}", messages.cannot_read(e))
+        }
+        process::exit(2)
+    };
+
+    loop {
+        print!("{}", messages.ask_starting_game());
+        io::stdout().flush().unwrap();
+
+        let input = read_answer();
+
+        match Game::from_position_or_moves(&input) {
+            Ok(game) if game.result().is_some() => println!("{}", messages.game_already_over()),
+            Ok(game) => return game,
+            Err(e) => println!("{}", messages.cannot_read(e))
+        }
+    }
+}
+
+
+fn load_saved_game(messages: &Messages, path: &str) -> Option<(Setup, Game)> {
+    let saved_game = match record::load(Path::new(path)) {
+        Ok(saved_games) => saved_games.into_iter().last(),
+        Err(e) => {
+            println!("{}", messages.load_failed(e));
+            return None
+        }
+    };
+
+    match saved_game {
+        None => {
+            println!("{}", messages.no_games_in_file());
+            None
+        },
+        Some(saved_game) if saved_game.game.result().is_some() => {
+            println!("{}", messages.game_already_over());
+            None
         },
-        Evaluation::Draw => println!("引き分けだね。楽しい対局をありがとう。"),
-        _ => panic!("Something went wrong.")
+        Some(saved_game) => match Setup::from_settings(&saved_game.settings) {
+            Some(setup) => Some((setup, saved_game.game)),
+            None => {
+                println!("{}", messages.cannot_read_settings());
+                None
+            }
+        }
+    }
+}
+
+
+fn get_file_name(messages: &Messages) -> String {
+    print!("{}", messages.ask_file_name(DEFAULT_SAVE_FILE));
+    io::stdout().flush().unwrap();
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).expect("Failed to read line");
+
+    match input.trim() {
+        "" => DEFAULT_SAVE_FILE.to_string(),
+        name => name.to_string()
     }
 }
 
@@ -89,63 +650,35 @@ fn clear_screen() {
 }
 
 
-fn get_user_coordinates(position: &Position) -> Coordinates {
-    loop {
-        let file: usize;
-        let rank: usize;
-        let coordinates: Coordinates;
-
-        loop {
-            print!("縦列の番号は？[1-4]: ");
-            io::stdout().flush().unwrap();
-
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).expect("Failed to read line");
-            input = input.trim().to_string();
-
-            match &input[..] {
-                "1" => file = 0,
-                "2" => file = 1,
-                "3" => file = 2,
-                "4" => file = 3,
-                _ => {
-                    println!("使える数字を打ってね。[1-4]");
-                    continue;
-                }
-            }
+fn print_help(messages: &Messages) {
+    for line in messages.help() {
+        println!("{}", line);
+    }
+}
 
-            break;
-        };
-        
-        loop {
-            print!("横列の番号は？[1-4]: ");
-            io::stdout().flush().unwrap();
-
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).expect("Failed to read line");
-            input = input.trim().to_string();
-
-            match &input[..] {
-                "1" => rank = 0,
-                "2" => rank = 1,
-                "3" => rank = 2,
-                "4" => rank = 3,
-                _ => {
-                    println!("使える数字を打ってね。[1-4]");
-                    continue;
-                }
-            }
 
-            break;
-        }
+/// Reads a line from the user until it parses as a move or command. Reaching the end of input counts as quitting.
+fn get_command(messages: &Messages) -> Command {
+    loop {
+        print!("{}", messages.move_prompt());
+        io::stdout().flush().unwrap();
 
-        coordinates = Coordinates::new(file, rank);
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input).expect("Failed to read line") == 0 {
+            return Command::Quit
+        };
 
-        if position.can_play(coordinates) {
-            return coordinates
-        } else {
-            println!("そこには置けないな。どこに置きたい？");
-            continue;
+        match command::parse_command(&input) {
+            Ok(command) => return command,
+            Err(ParseCommandError::Empty) => println!("{}", messages.enter_move_or_command()),
+            Err(ParseCommandError::UnknownCommand(name)) => println!("{}", messages.unknown_command(&name)),
+            Err(ParseCommandError::UnexpectedArgument(name)) => println!("{}", messages.unexpected_argument(&name)),
+            Err(ParseCommandError::InvalidMove(e)) => match e {
+                ParseCoordinatesError::InvalidFile(_) => println!("{}", messages.invalid_file()),
+                ParseCoordinatesError::InvalidRank(_) => println!("{}", messages.invalid_rank()),
+                ParseCoordinatesError::InvalidFloor(_) => println!("{}", messages.invalid_floor()),
+                ParseCoordinatesError::Malformed(_) => println!("{}", messages.malformed_move())
+            }
         }
     }
 }
@@ -0,0 +1,479 @@
+use yonmokun::game::Participant;
+use std::env;
+use std::fmt::Display;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    Japanese,
+    English
+}
+
+impl Locale {
+    /// Parses a language code such as "en", "ja" or "en_GB.UTF-8".
+    pub fn from_code(code: &str) -> Option<Locale> {
+        let language = code.split(['_', '-', '.']).next().unwrap_or("").to_lowercase();
+        match language.as_str() {
+            "ja" => Some(Locale::Japanese),
+            "en" => Some(Locale::English),
+            _ => None
+        }
+    }
+
+    /// Picks the locale from the LC_ALL, LC_MESSAGES or LANG environment variables, in that order, falling back to
+    /// Japanese if none of them name a supported language.
+    pub fn from_env() -> Locale {
+        ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Locale::from_code(&value))
+            .unwrap_or(Locale::Japanese)
+    }
+}
+
+
+/// The text shown by the CLI, in the chosen locale.
+#[derive(Debug, Clone, Copy)]
+pub struct Messages {
+    locale: Locale
+}
+
+impl Messages {
+    pub fn new(locale: Locale) -> Self {
+        Messages { locale }
+    }
+
+    fn pick<'a>(&self, japanese: &'a str, english: &'a str) -> &'a str {
+        match self.locale {
+            Locale::Japanese => japanese,
+            Locale::English => english
+        }
+    }
+
+    pub fn greeting(&self) -> &'static str {
+        self.pick("やあ、僕の名はよんも君。一緒に三次元四目並べを遊ぼう！\n", "Hi, I'm Yonmokun. Let's play 3D connect four together!\n")
+    }
+
+    pub fn menu_play_engine(&self) -> &'static str {
+        self.pick("よんも君と対局", "Play against Yonmokun")
+    }
+
+    pub fn menu_hot_seat(&self) -> &'static str {
+        self.pick("二人で対局", "Two players on this terminal")
+    }
+
+    pub fn menu_engine_vs_engine(&self) -> &'static str {
+        self.pick("よんも君同士の対局", "Watch Yonmokun play itself")
+    }
+
+    pub fn menu_load(&self) -> &'static str {
+        self.pick("保存した対局の続き", "Continue a saved game")
+    }
+
+    pub fn choose_mode(&self, max: usize) -> String {
+        match self.locale {
+            Locale::Japanese => format!("遊ぶモードを選んでね。[1-{}]: ", max),
+            Locale::English => format!("Choose a mode. [1-{}]: ", max)
+        }
+    }
+
+    pub fn choose_between(&self, max: usize) -> String {
+        match self.locale {
+            Locale::Japanese if max == 2 => "１か２を選んでね。".to_string(),
+            Locale::Japanese => format!("１から{}の中から選んでね。", max),
+            Locale::English => format!("Please choose a number from 1 to {}.", max)
+        }
+    }
+
+    pub fn yes_or_no(&self, question: &str) -> String {
+        match self.locale {
+            Locale::Japanese => format!("{}[1: はい, 2: いいえ]: ", question),
+            Locale::English => format!("{} [1: yes, 2: no]: ", question)
+        }
+    }
+
+    pub fn ask_hints(&self) -> &'static str {
+        self.pick("よんも君にヒントを出してもらう？", "Should Yonmokun give hints?")
+    }
+
+    pub fn ask_auto_play(&self) -> &'static str {
+        self.pick("自動で進める？（「いいえ」ならEnterで一手ずつ進むよ）", "Play automatically? (If not, press Enter to step through each move)")
+    }
+
+    pub fn level_name(&self, level: u8) -> &'static str {
+        match level {
+            1 => self.pick("はじめて", "Beginner"),
+            2 => self.pick("優しい", "Gentle"),
+            3 => self.pick("ふつう", "Normal"),
+            4 => self.pick("強い", "Strong"),
+            5 => self.pick("とても強い", "Very strong"),
+            6 => self.pick("本気", "Serious"),
+            _ => self.pick("全力", "All out")
+        }
+    }
+
+    pub fn choose_level(&self, stone: Option<&str>, max: u8) -> String {
+        match (self.locale, stone) {
+            (Locale::Japanese, Some(stone)) => format!("{}のよんも君の強さを選んでね。[1-{}]: ", stone, max),
+            (Locale::Japanese, None) => format!("よんも君の強さを選んでね。[1-{}]: ", max),
+            (Locale::English, Some(stone)) => format!("Choose how strongly Yonmokun plays {}. [1-{}]: ", stone, max),
+            (Locale::English, None) => format!("Choose how strongly Yonmokun plays. [1-{}]: ", max)
+        }
+    }
+
+    pub fn first_player_option(&self) -> &'static str {
+        self.pick("先手（君から打つ）", "First (you move first)")
+    }
+
+    pub fn second_player_option(&self) -> &'static str {
+        self.pick("後手（僕から打つ）", "Second (Yonmokun moves first)")
+    }
+
+    pub fn ask_first_or_second(&self) -> &'static str {
+        self.pick("先手と後手、どっちがいい？[1,2]: ", "Do you want to move first or second? [1,2]: ")
+    }
+
+    pub fn ask_colour(&self) -> &'static str {
+        self.pick("○と●、どっちを使う？[1: ○, 2: ●]: ", "Which pieces do you want to play? [1: ○, 2: ●]: ")
+    }
+
+    pub fn ask_starting_game(&self) -> &'static str {
+        self.pick(
+            "棋譜か局面文字列から続けるなら入力してね。（例: a1 d4 b2）[空欄で最初から]: ",
+            "Enter a move list or position string to continue from (e.g. a1 d4 b2), or leave blank to start afresh: "
+        )
+    }
+
+    pub fn game_already_over(&self) -> &'static str {
+        self.pick("その対局はもう終わっているよ。", "That game is already over.")
+    }
+
+    pub fn cannot_read(&self, error: impl Display) -> String {
+        match self.locale {
+            Locale::Japanese => format!("それは読めないな。({})", error),
+            Locale::English => format!("I can't read that. ({})", error)
+        }
+    }
+
+    pub fn ask_file_name(&self, default: &str) -> String {
+        match self.locale {
+            Locale::Japanese => format!("ファイル名は？[空欄で{}]: ", default),
+            Locale::English => format!("File name? [blank for {}]: ", default)
+        }
+    }
+
+    pub fn load_failed(&self, error: impl Display) -> String {
+        match self.locale {
+            Locale::Japanese => format!("読み込めなかったよ。({})", error),
+            Locale::English => format!("Couldn't load the game. ({})", error)
+        }
+    }
+
+    pub fn no_games_in_file(&self) -> &'static str {
+        self.pick("そのファイルには対局がないよ。", "There are no games in that file.")
+    }
+
+    pub fn cannot_read_settings(&self) -> &'static str {
+        self.pick("その対局の設定が読めないよ。", "I can't read the settings for that game.")
+    }
+
+    pub fn your_turn(&self) -> &'static str {
+        self.pick("君の番だよ。", "Your turn.")
+    }
+
+    pub fn player_turn(&self, stone: &str) -> String {
+        match self.locale {
+            Locale::Japanese => format!("{}の番だよ。", stone),
+            Locale::English => format!("{} to move.", stone)
+        }
+    }
+
+    pub fn move_prompt(&self) -> &'static str {
+        self.pick("どこに打つ？（例: b3, 23。「help」でコマンド一覧）: ", "Your move? (e.g. b3, 23; \"help\" lists the commands): ")
+    }
+
+    pub fn cannot_play_there(&self) -> &'static str {
+        self.pick("そこには置けないな。どこに置きたい？", "That column is full. Where else would you like to play?")
+    }
+
+    pub fn lands_on_floor(&self, coordinates: impl Display, floor: usize) -> String {
+        match self.locale {
+            Locale::Japanese => format!("{}に打つと{}階に落ちるよ。", coordinates, floor),
+            Locale::English => format!("A piece played at {} lands on floor {}.", coordinates, floor)
+        }
+    }
+
+    pub fn nothing_to_undo(&self) -> &'static str {
+        self.pick("まだ戻せる手がないよ。", "There are no moves to take back.")
+    }
+
+    pub fn undone(&self) -> &'static str {
+        self.pick("一手戻したよ。", "Took back a move.")
+    }
+
+    pub fn nothing_to_redo(&self) -> &'static str {
+        self.pick("進める手がないよ。", "There are no moves to replay.")
+    }
+
+    pub fn redone(&self) -> &'static str {
+        self.pick("一手進めたよ。", "Replayed a move.")
+    }
+
+    pub fn saved(&self, path: &str) -> String {
+        match self.locale {
+            Locale::Japanese => format!("{}に保存したよ。", path),
+            Locale::English => format!("Saved to {}.", path)
+        }
+    }
+
+    pub fn save_failed(&self, error: impl Display) -> String {
+        match self.locale {
+            Locale::Japanese => format!("保存できなかったよ。({})", error),
+            Locale::English => format!("Couldn't save the game. ({})", error)
+        }
+    }
+
+    pub fn loaded(&self, path: &str) -> String {
+        match self.locale {
+            Locale::Japanese => format!("{}を読み込んだよ。", path),
+            Locale::English => format!("Loaded {}.", path)
+        }
+    }
+
+    pub fn press_enter(&self) -> &'static str {
+        self.pick("Enterで次の手に進むよ。", "Press Enter for the next move.")
+    }
+
+    pub fn engine_played(&self, coordinates: impl Display) -> String {
+        match self.locale {
+            Locale::Japanese => format!("僕はここに打ったよ。{}", coordinates),
+            Locale::English => format!("I played {}.", coordinates)
+        }
+    }
+
+    pub fn player_played(&self, stone: &str, coordinates: impl Display) -> String {
+        match self.locale {
+            Locale::Japanese => format!("{}はここに打ったよ。{}", stone, coordinates),
+            Locale::English => format!("{} played {}.", stone, coordinates)
+        }
+    }
+
+    pub fn confidence(&self, percent: f32) -> String {
+        match self.locale {
+            Locale::Japanese => format!("自信係数: {:.0}%", percent),
+            Locale::English => format!("Confidence: {:.0}%", percent)
+        }
+    }
+
+    pub fn search_depth(&self, depth: u8) -> String {
+        match self.locale {
+            Locale::Japanese => format!("読みの深さ:     {}", depth),
+            Locale::English => format!("search depth:            {}", depth)
+        }
+    }
+
+    pub fn computation_time(&self, millis: u128) -> String {
+        match self.locale {
+            Locale::Japanese => format!("計算時間 (ms):  {}", millis),
+            Locale::English => format!("computation time (ms):   {}", millis)
+        }
+    }
+
+    pub fn positions_traversed(&self, num_positions: u32) -> String {
+        match self.locale {
+            Locale::Japanese => format!("探索した局面数: {}", num_positions),
+            Locale::English => format!("num positions traversed: {}", num_positions)
+        }
+    }
+
+    pub fn thinking(&self, depth: u8, best_move: impl Display, percent: f32) -> String {
+        match self.locale {
+            Locale::Japanese => format!("考え中… 深さ{} 最善手{} 自信係数{:.0}%", depth, best_move, percent),
+            Locale::English => format!("Thinking... depth {}, best move {}, confidence {:.0}%", depth, best_move, percent)
+        }
+    }
+
+    pub fn hint(&self, coordinates: impl Display) -> String {
+        match self.locale {
+            Locale::Japanese => format!("よんも君のおすすめは{}だよ。", coordinates),
+            Locale::English => format!("Yonmokun suggests {}.", coordinates)
+        }
+    }
+
+    pub fn principal_variation(&self, line: &str) -> String {
+        match self.locale {
+            Locale::Japanese => format!("読み筋: {}", line),
+            Locale::English => format!("Expected line: {}", line)
+        }
+    }
+
+    pub fn goodbye(&self) -> &'static str {
+        self.pick("またね！", "See you!")
+    }
+
+    pub fn player_wins(&self, stone: &str) -> String {
+        match self.locale {
+            Locale::Japanese => format!("{}の勝ち！楽しい対局をありがとう。", stone),
+            Locale::English => format!("{} wins! Thanks for the game.", stone)
+        }
+    }
+
+    pub fn human_wins(&self) -> &'static str {
+        self.pick("負けました！楽しい対局をありがとう。", "You win! Thanks for the game.")
+    }
+
+    pub fn engine_wins(&self) -> &'static str {
+        self.pick("やった、僕の勝ち！楽しい対局をありがとう。", "Yay, I win! Thanks for the game.")
+    }
+
+    pub fn winning_line(&self, cells: &str) -> String {
+        match self.locale {
+            Locale::Japanese => format!("決まり手: {}", cells),
+            Locale::English => format!("Winning line: {}", cells)
+        }
+    }
+
+    pub fn draw(&self) -> &'static str {
+        self.pick("引き分けだね。楽しい対局をありがとう。", "It's a draw. Thanks for the game.")
+    }
+
+    pub fn game_record(&self) -> &'static str {
+        self.pick("棋譜:", "Game record:")
+    }
+
+    pub fn participant(&self, participant: Participant) -> &'static str {
+        match participant {
+            Participant::Human => self.pick("君", "you"),
+            Participant::Engine => self.pick("僕", "me"),
+            Participant::Unknown => self.pick("？", "?")
+        }
+    }
+
+    pub fn game_duration(&self, minutes: u64, seconds: u64) -> String {
+        match self.locale {
+            Locale::Japanese => format!("対局時間: {}分{}秒", minutes, seconds),
+            Locale::English => format!("Game length: {}m {}s", minutes, seconds)
+        }
+    }
+
+    pub fn move_list(&self, move_list: impl Display) -> String {
+        match self.locale {
+            Locale::Japanese => format!("共有用: {}", move_list),
+            Locale::English => format!("Move list: {}", move_list)
+        }
+    }
+
+    pub fn final_position(&self, position: impl Display) -> String {
+        match self.locale {
+            Locale::Japanese => format!("最終局面: {}", position),
+            Locale::English => format!("Final position: {}", position)
+        }
+    }
+
+    pub fn ask_review(&self) -> &'static str {
+        self.pick("対局を振り返る？", "Review the game?")
+    }
+
+    pub fn reviewing(&self) -> &'static str {
+        self.pick("振り返り中...", "Reviewing...")
+    }
+
+    pub fn confidence_swing(&self, before: f32, after: f32) -> String {
+        match self.locale {
+            Locale::Japanese => format!("自信係数: {:>3.0}% → {:>3.0}%", before, after),
+            Locale::English => format!("confidence: {:>3.0}% → {:>3.0}%", before, after)
+        }
+    }
+
+    pub fn blunder(&self, better_move: impl Display) -> String {
+        match self.locale {
+            Locale::Japanese => format!("悪手！{}なら負けなかったよ。", better_move),
+            Locale::English => format!("Blunder! {} would not have lost.", better_move)
+        }
+    }
+
+    pub fn num_blunders(&self, num_blunders: usize) -> String {
+        match self.locale {
+            Locale::Japanese => format!("悪手は{}手あったよ。", num_blunders),
+            Locale::English if num_blunders == 1 => "There was 1 blunder.".to_string(),
+            Locale::English => format!("There were {} blunders.", num_blunders)
+        }
+    }
+
+    pub fn help(&self) -> &'static [&'static str] {
+        match self.locale {
+            Locale::Japanese => &[
+                "b3や23のように、縦列（a-dか1-4）と横列（1-4）で打つ場所を指定してね。",
+                "使えるコマンド:",
+                "  undo (待った)          一手戻す",
+                "  redo (進める)          戻した手を進める",
+                "  hint (ヒント)          よんも君のおすすめを見る",
+                "  save [ファイル名] (保存)  対局を保存する",
+                "  load [ファイル名] (読込)  保存した対局を読み込む",
+                "  flip (反転)            盤を反対側から見る",
+                "  quit (終了)            対局をやめる",
+                "  help (ヘルプ)          この説明を見る"
+            ],
+            Locale::English => &[
+                "Play a move by giving its file (a-d or 1-4) and rank (1-4), as in b3 or 23.",
+                "Commands:",
+                "  undo            take back a move",
+                "  redo            replay a move that was taken back",
+                "  hint            ask Yonmokun for a suggestion",
+                "  save [file]     save the game",
+                "  load [file]     load a saved game",
+                "  flip            view the board from the other side",
+                "  quit            stop playing",
+                "  help            show this help"
+            ]
+        }
+    }
+
+    pub fn enter_move_or_command(&self) -> &'static str {
+        self.pick("打つ場所かコマンドを入力してね。", "Enter a move or a command.")
+    }
+
+    pub fn unknown_command(&self, name: &str) -> String {
+        match self.locale {
+            Locale::Japanese => format!("「{}」は分からないな。「help」でコマンドの一覧を見てね。", name),
+            Locale::English => format!("I don't know \"{}\". Type \"help\" for a list of commands.", name)
+        }
+    }
+
+    pub fn unexpected_argument(&self, name: &str) -> String {
+        match self.locale {
+            Locale::Japanese => format!("「{}」の後には何もいらないよ。", name),
+            Locale::English => format!("\"{}\" doesn't take anything after it.", name)
+        }
+    }
+
+    pub fn invalid_file(&self) -> &'static str {
+        self.pick("縦列はa-dか1-4で指定してね。", "The file should be a-d or 1-4.")
+    }
+
+    pub fn invalid_rank(&self) -> &'static str {
+        self.pick("横列は1-4で指定してね。", "The rank should be 1-4.")
+    }
+
+    pub fn invalid_floor(&self) -> &'static str {
+        self.pick("階は1-4で指定してね。", "The floor should be 1-4.")
+    }
+
+    pub fn malformed_move(&self) -> &'static str {
+        self.pick("b3や23のように打ってね。", "Give your move like b3 or 23.")
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::Locale;
+
+    #[test]
+    fn test_locale_from_code() {
+        assert_eq!(Locale::from_code("en"), Some(Locale::English));
+        assert_eq!(Locale::from_code("en_GB.UTF-8"), Some(Locale::English));
+        assert_eq!(Locale::from_code("ja_JP.UTF-8"), Some(Locale::Japanese));
+        assert_eq!(Locale::from_code("C.UTF-8"), None);
+    }
+}
@@ -0,0 +1,290 @@
+use crate::batch::OutputFormat;
+use yonmokun::difficulty::MAX_LEVEL;
+use yonmokun::evaluation::{SearchLimits, MAX_DEPTH};
+use crate::messages::Locale;
+use yonmokun::position::Player;
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+
+pub const USAGE: &str = "\
+Usage: yonmokun [options]
+       yonmokun analyse [options] [file]
+       yonmokun engine [options]
+       yonmokun serve [options]
+
+`analyse` analyses each position string or move list in the file, or read from standard input, one per line, or
+the current position of each game in a file of saved games.
+`engine` speaks the text engine protocol on standard input and output, for use by other front ends.
+`serve` answers HTTP requests with JSON on localhost; see src/server.rs for the endpoints.
+
+Options:
+  --white <human|engine[:level]>  who plays ○; skips the mode menu
+  --black <human|engine[:level]>  who plays ●; skips the mode menu
+  --level <1-7>                   the engine's level
+  --depth <plies>                 search this deep, whatever the level
+  --time <seconds>                search for this long, whatever the level
+  --threads <n>                   split the search between n threads
+  --first <white|black>           the colour that moves first
+  --position <moves|position>     start from a move list or position string
+  --hints                         have the engine give hints to human players
+  --auto-play                     play engine moves without waiting for Enter
+  --ponder                        let the engine think on the user's time
+  --compact                       stack the floors for narrow terminals
+  --no-colour                     draw the board without colours
+  --format <csv|json>             how `analyse` writes its results
+  --port <port>                   the port `serve` listens on (8080 by default)
+  --lang <ja|en>                  the interface language
+  --help                          show this help";
+
+
+/// What the program should do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// Play interactively.
+    #[default]
+    Play,
+    /// Analyse a batch of positions.
+    Analyse,
+    /// Speak the text engine protocol.
+    Engine,
+    /// Serve the HTTP API.
+    Serve
+}
+
+
+/// Who plays one side, as given on the command line. An engine given without a level plays at the level from
+/// `--level`, or at one chosen interactively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControllerOption {
+    Human,
+    Engine { level: Option<u8> }
+}
+
+
+/// The command-line options. Anything left unset is asked for interactively.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Options {
+    pub show_usage: bool,
+    pub mode: Mode,
+    /// The file `analyse` reads positions from, or standard input if not given.
+    pub input: Option<String>,
+    pub format: OutputFormat,
+    pub port: Option<u16>,
+    pub locale: Option<Locale>,
+    pub white: Option<ControllerOption>,
+    pub black: Option<ControllerOption>,
+    pub level: Option<u8>,
+    pub depth: Option<u8>,
+    pub time_budget: Option<Duration>,
+    pub threads: Option<usize>,
+    pub first_player: Option<Player>,
+    pub start: Option<String>,
+    pub show_hints: bool,
+    pub auto_play: bool,
+    pub ponder: bool,
+    pub compact: bool,
+    pub no_colour: bool
+}
+
+impl Options {
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Options, ParseOptionsError> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+                _ => (arg, None)
+            };
+
+            match flag.as_str() {
+                "--help" | "-h" => {
+                    options.show_usage = true;
+                    continue;
+                },
+                "--hints" => {
+                    options.show_hints = true;
+                    continue;
+                },
+                "--auto-play" => {
+                    options.auto_play = true;
+                    continue;
+                },
+                "--ponder" => {
+                    options.ponder = true;
+                    continue;
+                },
+                "--compact" => {
+                    options.compact = true;
+                    continue;
+                },
+                "--no-colour" | "--no-color" => {
+                    options.no_colour = true;
+                    continue;
+                },
+                "analyse" | "analyze" | "engine" | "serve" if options.mode == Mode::Play => {
+                    options.mode = match flag.as_str() {
+                        "engine" => Mode::Engine,
+                        "serve" => Mode::Serve,
+                        _ => Mode::Analyse
+                    };
+                    continue;
+                },
+                positional if options.mode == Mode::Analyse && options.input.is_none() && (positional == "-" || !positional.starts_with('-')) => {
+                    options.input = Some(positional.to_string()).filter(|input| input != "-");
+                    continue;
+                },
+                positional if !positional.starts_with('-') => return Err(ParseOptionsError::UnexpectedArgument(flag)),
+                _ => {}
+            };
+
+            let value = match inline_value.or_else(|| args.next()) {
+                Some(value) => value,
+                None if is_known_flag(&flag) => return Err(ParseOptionsError::MissingValue(flag)),
+                None => return Err(ParseOptionsError::UnknownFlag(flag))
+            };
+            let invalid = || ParseOptionsError::InvalidValue { flag: flag.clone(), value: value.clone() };
+
+            match flag.as_str() {
+                "--lang" => options.locale = Some(Locale::from_code(&value).ok_or_else(invalid)?),
+                "--white" => options.white = Some(parse_controller(&value).ok_or_else(invalid)?),
+                "--black" => options.black = Some(parse_controller(&value).ok_or_else(invalid)?),
+                "--level" => options.level = Some(parse_level(&value).ok_or_else(invalid)?),
+                "--depth" => options.depth = Some(value.parse().ok().filter(|depth| *depth > 0).ok_or_else(invalid)?),
+                "--time" => {
+                    let seconds: f64 = value.parse().ok().filter(|seconds: &f64| *seconds > 0.0 && seconds.is_finite()).ok_or_else(invalid)?;
+                    options.time_budget = Some(Duration::from_secs_f64(seconds));
+                },
+                "--threads" => options.threads = Some(value.parse().ok().filter(|threads| *threads > 0).ok_or_else(invalid)?),
+                "--first" => options.first_player = Some(match value.as_str() {
+                    "white" | "w" => Player::White,
+                    "black" | "b" => Player::Black,
+                    _ => return Err(invalid())
+                }),
+                "--position" => options.start = Some(value),
+                "--port" => options.port = Some(value.parse().map_err(|_| invalid())?),
+                "--format" => options.format = match value.as_str() {
+                    "csv" => OutputFormat::Csv,
+                    "json" => OutputFormat::Json,
+                    _ => return Err(invalid())
+                },
+                _ => return Err(ParseOptionsError::UnknownFlag(flag))
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// Whether the players were given on the command line, so that the mode menu can be skipped.
+    pub fn has_players(&self) -> bool {
+        self.white.is_some() || self.black.is_some()
+    }
+
+    /// Applies the depth, time and thread options on top of the given limits. A time budget given without a depth
+    /// lets the search deepen for as long as the budget allows.
+    pub fn search_limits(&self, limits: SearchLimits) -> SearchLimits {
+        let depth = match (self.depth, self.time_budget) {
+            (Some(depth), _) => depth,
+            (None, Some(_)) => MAX_DEPTH,
+            (None, None) => limits.depth
+        };
+
+        SearchLimits {
+            depth,
+            time_budget: self.time_budget.or(limits.time_budget),
+            threads: self.threads.unwrap_or(limits.threads)
+        }
+    }
+}
+
+
+fn is_known_flag(flag: &str) -> bool {
+    matches!(flag, "--format" | "--port" | "--lang" | "--white" | "--black" | "--level" | "--depth" | "--time" | "--threads" | "--first" | "--position")
+}
+
+fn parse_level(s: &str) -> Option<u8> {
+    s.parse().ok().filter(|level| (1..=MAX_LEVEL).contains(level))
+}
+
+fn parse_controller(s: &str) -> Option<ControllerOption> {
+    match s.split_once(':') {
+        None if s == "human" => Some(ControllerOption::Human),
+        None if s == "engine" => Some(ControllerOption::Engine { level: None }),
+        Some(("engine", level)) => parse_level(level).map(|level| ControllerOption::Engine { level: Some(level) }),
+        _ => None
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseOptionsError {
+    UnknownFlag(String),
+    MissingValue(String),
+    UnexpectedArgument(String),
+    InvalidValue { flag: String, value: String }
+}
+
+impl fmt::Display for ParseOptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseOptionsError::UnknownFlag(flag) => write!(f, "unknown option \"{}\"", flag),
+            ParseOptionsError::MissingValue(flag) => write!(f, "\"{}\" needs a value", flag),
+            ParseOptionsError::UnexpectedArgument(arg) => write!(f, "unexpected argument \"{}\"", arg),
+            ParseOptionsError::InvalidValue { flag, value } => write!(f, "invalid value \"{}\" for \"{}\"", value, flag)
+        }
+    }
+}
+
+impl Error for ParseOptionsError {}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{ControllerOption, Mode, Options, ParseOptionsError};
+    use crate::batch::OutputFormat;
+    use yonmokun::difficulty::Difficulty;
+    use yonmokun::evaluation::{SearchLimits, MAX_DEPTH};
+    use crate::messages::Locale;
+    use yonmokun::position::Player;
+    use std::time::Duration;
+
+    fn parse(args: &[&str]) -> Result<Options, ParseOptionsError> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = parse(&["--white", "human", "--black=engine:3", "--first", "black", "--lang", "en", "--hints"]).unwrap();
+        assert_eq!(options.white, Some(ControllerOption::Human));
+        assert_eq!(options.black, Some(ControllerOption::Engine { level: Some(3) }));
+        assert_eq!(options.first_player, Some(Player::Black));
+        assert_eq!(options.locale, Some(Locale::English));
+        assert!(options.show_hints && options.has_players());
+        assert!(!options.compact && parse(&["--compact", "--no-color"]).is_ok_and(|options| options.compact && options.no_colour));
+
+        let options = parse(&["--time", "1.5", "--threads", "4"]).unwrap();
+        let limits = options.search_limits(SearchLimits { depth: 3, time_budget: None, threads: 1 });
+        assert_eq!(limits, SearchLimits { depth: 64, time_budget: Some(Duration::from_millis(1500)), threads: 4 });
+
+        assert_eq!(parse(&["--level", "9"]), Err(ParseOptionsError::InvalidValue { flag: "--level".to_string(), value: "9".to_string() }));
+        assert_eq!(parse(&["--depth"]), Err(ParseOptionsError::MissingValue("--depth".to_string())));
+        assert_eq!(parse(&["--colour", "white"]), Err(ParseOptionsError::UnknownFlag("--colour".to_string())));
+
+        let options = parse(&["analyse", "--format", "json", "study.txt"]).unwrap();
+        assert_eq!(options.mode, Mode::Analyse);
+        assert_eq!(options.format, OutputFormat::Json);
+        assert_eq!(options.input, Some("study.txt".to_string()));
+        assert_eq!(parse(&["study.txt"]), Err(ParseOptionsError::UnexpectedArgument("study.txt".to_string())));
+    }
+
+    #[test]
+    fn test_time_at_weak_levels() {
+        // Without a depth, the time budget is all that stops the deepening, even at a level with a shallow depth.
+        let options = parse(&["--black", "engine:1", "--time", "0.05"]).unwrap();
+        let limits = options.search_limits(Difficulty::level(1).unwrap().limits);
+        assert_eq!(limits.depth, MAX_DEPTH);
+        assert_eq!(limits.time_budget, Some(Duration::from_millis(50)));
+    }
+}
@@ -0,0 +1,76 @@
+use crate::evaluation::{self, AnalysisReport, SearchLimits, StopHandle};
+use crate::position::{Position, Coordinates, EngineError};
+use std::thread::{self, JoinHandle};
+
+
+/// A search running in the background on the position the engine expects to face next, started while the opponent
+/// is still thinking about their move. If the opponent plays the expected reply, the engine can pick up the search
+/// where it got to instead of starting from scratch. Dropping a ponderer stops its search.
+pub struct Ponderer {
+    position: Position,
+    limits: SearchLimits,
+    stop: StopHandle,
+    handle: Option<JoinHandle<Result<AnalysisReport, EngineError>>>
+}
+
+impl Ponderer {
+    /// Starts searching the position that `expected_reply` would lead to, with the limits the engine will use for
+    /// its next move.
+    pub fn start(position: &Position, expected_reply: Coordinates, limits: SearchLimits) -> Option<Ponderer> {
+        if position.is_terminal() || !position.can_play(expected_reply) {
+            return None
+        };
+
+        let position = position.play(expected_reply);
+        if position.is_terminal() {
+            return None
+        };
+
+        let stop = StopHandle::new();
+        let handle = {
+            let (position, stop) = (position.clone(), stop.clone());
+            thread::spawn(move || evaluation::analyse_until_stopped(&position, limits, &stop))
+        };
+
+        Some(Ponderer { position, limits, stop, handle: Some(handle) })
+    }
+
+    /// Returns the result of the search if it was on the given position with the given limits, waiting for it to
+    /// finish if need be. Otherwise the guess was wrong, and the search is abandoned.
+    pub fn finish(mut self, position: &Position, limits: SearchLimits) -> Option<AnalysisReport> {
+        if *position != self.position || limits != self.limits {
+            return None
+        };
+
+        self.handle.take()?.join().ok()?.ok()
+    }
+}
+
+impl Drop for Ponderer {
+    fn drop(&mut self) {
+        self.stop.stop();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::Ponderer;
+    use crate::evaluation::{self, SearchLimits};
+    use crate::position::{Position, Coordinates};
+
+    #[test]
+    fn test_ponder_hit_and_miss() {
+        let limits = SearchLimits { depth: 3, time_budget: None, threads: 1 };
+        let position = Position::new().play(Coordinates::new(1, 1));
+
+        let ponderer = Ponderer::start(&position, Coordinates::new(2, 2), limits).unwrap();
+        let expected = position.play(Coordinates::new(2, 2));
+        let report = ponderer.finish(&expected, limits).unwrap();
+        assert_eq!(report.best_move, evaluation::analyse(&expected, 3).unwrap().best_move);
+        assert_eq!(report.depth, 3);
+
+        let ponderer = Ponderer::start(&position, Coordinates::new(2, 2), limits).unwrap();
+        assert!(ponderer.finish(&position.play(Coordinates::new(0, 0)), limits).is_none());
+    }
+}
@@ -1,48 +1,54 @@
 use std::cmp::Ordering;
+use std::error::Error;
 use std::fmt;
 use std::ops::{AddAssign, Neg};
+use std::str::FromStr;
 
 
+#[derive(Clone)]
 pub struct Position {
     pub active_player: Player,
     board: [[[Slot; 4]; 4]; 4],
-    num_moves_played: u8
+    num_moves_played: u8,
+    undo_stack: [u8; 64],
+    undo_stack_len: u8
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position::new()
+    }
 }
 
 impl Position {
     pub fn new() -> Self {
+        Position::starting_with(Player::White)
+    }
+
+    /// Returns an empty board with the given player to move first.
+    pub fn starting_with(first_player: Player) -> Self {
         let board = [[[Slot::Empty; 4]; 4]; 4];
-        let active_player = Player::White;
+        let active_player = first_player;
         let num_moves_played = 0;
-        Position{board, active_player, num_moves_played}
+        Position{board, active_player, num_moves_played, undo_stack: [0; 64], undo_stack_len: 0}
     }
 
-    pub fn print(&self) {
-        print!("\n");
-        for floor in (0..4).rev() {
-            for row in (0..4).rev() {
-                println!("{} {}  {}  {}  {}", row+1, self.board[floor][0][row], self.board[floor][1][row], self.board[floor][2][row], self.board[floor][3][row]);
-            }
-            println!("  1  2  3  4\n");
+    /// Returns the player whose piece is in the cell, or `None` if it is empty.
+    pub fn piece_at(&self, cell: Cell) -> Option<Player> {
+        match self.board[cell.floor][cell.coordinates.file][cell.coordinates.rank] {
+            Slot::Occupied(player) => Some(player),
+            Slot::Empty => None
         }
     }
 
     pub fn get_static_evaluation(&self) -> Evaluation {
         let mut heuristic_score = 0;
 
-        match self.evaluate_straight_lines() {
-            Evaluation::HeuristicScore(s) => heuristic_score += s,
-            terminal_eval => return terminal_eval
-        }
-
-        match self.evaluate_first_degree_diagonals() {
-            Evaluation::HeuristicScore(s) => heuristic_score += s,
-            terminal_eval => return terminal_eval
-        }
-
-        match self.evaluate_second_degree_diagonals() {
-            Evaluation::HeuristicScore(s) => heuristic_score += s,
-            terminal_eval => return terminal_eval
+        for cells in &LINES {
+            match self.line_score(self.line_state(cells)) {
+                Some(score) => heuristic_score += score,
+                None => return Evaluation::Loss
+            }
         }
 
         if self.num_moves_played == 64 {
@@ -52,157 +58,158 @@ impl Position {
         Evaluation::HeuristicScore(heuristic_score)
     }
 
-    fn evaluate_straight_lines(&self) -> Evaluation {
-        let mut heuristic_score = 0;
+    /// Returns every one of the 76 lines on the board along with how far each player has got towards completing it.
+    pub fn lines(&self) -> impl Iterator<Item = Line> + '_ {
+        LINES.iter().map(|cells| Line { cells: *cells, state: self.line_state(cells) })
+    }
 
-        for fixed_index_1 in 0..4 {
-            for fixed_index_2 in 0..4 {
-                for axis in 0..3 {
-                    let mut linestate = LineState::Uncontrolled;
-
-                    for moving_index in 0..4 {
-                        linestate += match axis {
-                            0 => self.board[fixed_index_1][fixed_index_2][moving_index], // along columns
-                            1 => self.board[fixed_index_1][moving_index][fixed_index_2], // along rows
-                            2 => self.board[moving_index][fixed_index_1][fixed_index_2], // along floors
-                            _ => panic!("Something went wrong.")
-                        }
-                    }
+    /// Returns the completed lines, of which there can be more than one if the winning move completed several at
+    /// once. There are none while the game is in progress or if it was drawn.
+    pub fn winning_lines(&self) -> Vec<Line> {
+        self.lines().filter(|line| matches!(line.state, LineState::Completed { .. })).collect()
+    }
 
-                    match linestate {
-                        LineState::Completed => return Evaluation::Loss,
-                        LineState::Controlled { controlling_player, degree } => match controlling_player == self.active_player {
-                            true => heuristic_score += degree,
-                            false => heuristic_score -= degree
-                        }
-                        _ => ()
-                    }
-                }
-            }
+    fn line_state(&self, cells: &[Cell; 4]) -> LineState {
+        let mut linestate = LineState::Uncontrolled;
+        for cell in cells {
+            linestate += self.board[cell.floor][cell.coordinates.file][cell.coordinates.rank];
         }
+        linestate
+    }
 
-        Evaluation::HeuristicScore(heuristic_score)
+    /// Scores a line for the player to move: positive if they control it, negative if their opponent does. Returns
+    /// `None` if the line is complete, which can only mean that the opponent has just won.
+    fn line_score(&self, linestate: LineState) -> Option<i8> {
+        match linestate {
+            LineState::Completed { .. } => None,
+            LineState::Controlled { controlling_player, degree } => match controlling_player == self.active_player {
+                true => Some(degree),
+                false => Some(-degree)
+            },
+            _ => Some(0)
+        }
     }
 
-    fn evaluate_first_degree_diagonals(&self) -> Evaluation {
-        let mut heuristic_score = 0;
+    /// Returns the position after the given move.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the move cannot be played; see `try_play` for a version that does not.
+    pub fn play(&self, coordinates: Coordinates) -> Position {
+        let mut new_position = self.clone();
+        new_position.make_move(coordinates);
+        new_position
+    }
 
-        for fixed_index in 0..4 {
-            for fixed_axis in 0..3 {
-                let mut linestate_1 = LineState::Uncontrolled;
-                let mut linestate_2 = LineState::Uncontrolled;
-
-                for moving_index in 0..4 {
-                    linestate_1 += match fixed_axis {
-                        0 => self.board[moving_index][moving_index][fixed_index],
-                        1 => self.board[moving_index][fixed_index][moving_index],
-                        2 => self.board[fixed_index][moving_index][moving_index],
-                        _ => panic!("Something went wrong.")
-                    };
-                    linestate_2 += match fixed_axis {
-                        0 => self.board[moving_index][3-moving_index][fixed_index],
-                        1 => self.board[moving_index][fixed_index][3-moving_index],
-                        2 => self.board[fixed_index][moving_index][3-moving_index],
-                        _ => panic!("Something went wrong.")
-                    }
-                }
+    /// Returns the position after the given move, or an error if the column is full or the game is already over.
+    pub fn try_play(&self, coordinates: Coordinates) -> Result<Position, EngineError> {
+        let mut new_position = self.clone();
+        new_position.try_make_move(coordinates)?;
+        Ok(new_position)
+    }
 
-                match linestate_1 {
-                    LineState::Completed => return Evaluation::Loss,
-                    LineState::Controlled { controlling_player, degree } => match controlling_player == self.active_player {
-                        true => heuristic_score += degree,
-                        false => heuristic_score -= degree
-                    },
-                    _ => ()
-                }
+    /// Plays a move in place as `make_move` does, or returns an error if the column is full or the game is already
+    /// over, leaving the position as it was.
+    pub fn try_make_move(&mut self, coordinates: Coordinates) -> Result<(), EngineError> {
+        if self.is_terminal() {
+            return Err(EngineError::TerminalPosition)
+        };
+        if !self.can_play(coordinates) {
+            return Err(EngineError::IllegalMove(coordinates))
+        };
 
-                match linestate_2 {
-                    LineState::Completed => return Evaluation::Loss,
-                    LineState::Controlled { controlling_player, degree } => match controlling_player == self.active_player {
-                        true => heuristic_score += degree,
-                        false => heuristic_score -= degree
-                    },
-                    _ => ()
-                }
+        self.make_move(coordinates);
+        Ok(())
+    }
+
+    /// Plays a move in place, remembering it so that it can later be taken back with `unmake_move`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the column is full; see `try_make_move` for a version that does not.
+    pub fn make_move(&mut self, coordinates: Coordinates) {
+        if !self.can_play(coordinates) {
+            panic!("Cannot play {}, {}", coordinates.file, coordinates.rank)
+        };
+
+        for floor in self.board.iter_mut() {
+            if floor[coordinates.file][coordinates.rank] == Slot::Empty {
+                floor[coordinates.file][coordinates.rank] = Slot::Occupied(self.active_player);
+                break;
             }
         }
 
-        Evaluation::HeuristicScore(heuristic_score)
+        self.undo_stack[self.undo_stack_len as usize] = (coordinates.file * 4 + coordinates.rank) as u8;
+        self.undo_stack_len += 1;
+        self.num_moves_played += 1;
+        self.active_player = -self.active_player;
     }
 
-    fn evaluate_second_degree_diagonals(&self) -> Evaluation {
-        let mut heuristic_score = 0;
+    /// Takes back the most recent move made with `make_move` and returns its coordinates, or `None` if there is
+    /// nothing left to take back.
+    pub fn unmake_move(&mut self) -> Option<Coordinates> {
+        if self.undo_stack_len == 0 {
+            return None
+        };
 
-        let mut linestate_1 = LineState::Uncontrolled;
-        let mut linestate_2 = LineState::Uncontrolled;
-        let mut linestate_3 = LineState::Uncontrolled;
-        let mut linestate_4 = LineState::Uncontrolled;
+        self.undo_stack_len -= 1;
+        let column = self.undo_stack[self.undo_stack_len as usize] as usize;
+        let coordinates = Coordinates::new(column / 4, column % 4);
 
-        for moving_index in 0..4 {
-            linestate_1 += self.board[moving_index][moving_index][moving_index];
-            linestate_2 += self.board[moving_index][moving_index][3-moving_index];
-            linestate_3 += self.board[moving_index][3-moving_index][moving_index];
-            linestate_4 += self.board[moving_index][3-moving_index][3-moving_index];
-        }
-
-        match linestate_1 {
-            LineState::Completed => return Evaluation::Loss,
-            LineState::Controlled { controlling_player, degree } => match controlling_player == self.active_player {
-                true => heuristic_score += degree,
-                false => heuristic_score -= degree
-            },
-            _ => ()
+        for floor in self.board.iter_mut().rev() {
+            if floor[coordinates.file][coordinates.rank] != Slot::Empty {
+                floor[coordinates.file][coordinates.rank] = Slot::Empty;
+                break;
+            }
         }
 
-        match linestate_2 {
-            LineState::Completed => return Evaluation::Loss,
-            LineState::Controlled { controlling_player, degree } => match controlling_player == self.active_player {
-                true => heuristic_score += degree,
-                false => heuristic_score -= degree
-            },
-            _ => ()
-        }
+        self.num_moves_played -= 1;
+        self.active_player = -self.active_player;
 
-        match linestate_3 {
-            LineState::Completed => return Evaluation::Loss,
-            LineState::Controlled { controlling_player, degree } => match controlling_player == self.active_player {
-                true => heuristic_score += degree,
-                false => heuristic_score -= degree
-            },
-            _ => ()
-        }
+        Some(coordinates)
+    }
 
-        match linestate_4 {
-            LineState::Completed => return Evaluation::Loss,
-            LineState::Controlled { controlling_player, degree } => match controlling_player == self.active_player {
-                true => heuristic_score += degree,
-                false => heuristic_score -= degree
-            },
-            _ => ()
-        }
+    /// Returns the cell of the most recent move made with `make_move`, or `None` if there is none, as for a position
+    /// read from a position string.
+    pub fn last_move(&self) -> Option<Cell> {
+        let column = *self.undo_stack[..self.undo_stack_len as usize].last()? as usize;
+        let coordinates = Coordinates::new(column / 4, column % 4);
+        Some(Cell { coordinates, floor: self.column_height(coordinates) - 1 })
+    }
 
-        Evaluation::HeuristicScore(heuristic_score)
+    /// Returns the number of pieces already stacked in the column at the given coordinates.
+    pub fn column_height(&self, coordinates: Coordinates) -> usize {
+        self.board.iter().take_while(|floor| floor[coordinates.file][coordinates.rank] != Slot::Empty).count()
     }
 
-    pub fn play(&self, coordinates: Coordinates) -> Position {
-        if !self.can_play(coordinates) {
-            panic!("Cannot play {}, {}", coordinates.file, coordinates.rank)
-        };
+    /// Returns the cells holding the given player's pieces, from the bottom floor up.
+    pub fn pieces(&self, player: Player) -> Vec<Cell> {
+        let mut cells = Vec::new();
 
-        let mut new_board = self.board.clone();
         for floor in 0..4 {
-            if new_board[floor][coordinates.file][coordinates.rank] == Slot::Empty {
-                new_board[floor][coordinates.file][coordinates.rank] = Slot::Occupied(self.active_player);
-                break;
+            for file in 0..4 {
+                for rank in 0..4 {
+                    if self.board[floor][file][rank] == Slot::Occupied(player) {
+                        cells.push(Cell { coordinates: Coordinates { file, rank }, floor });
+                    };
+                }
             }
         }
 
-        let new_active_player = match self.active_player {
-            Player::White => Player::Black,
-            Player::Black => Player::White
-        };
+        cells
+    }
 
-        Position { board: new_board, active_player: new_active_player, num_moves_played: self.num_moves_played+1 }
+    /// Returns the number of pieces on the board, which is the number of moves played from the empty board.
+    pub fn num_moves_played(&self) -> usize {
+        self.num_moves_played as usize
+    }
+
+    /// Returns the cell the next piece dropped into the column would land in, or `None` if the column is full.
+    pub fn landing_cell(&self, coordinates: Coordinates) -> Option<Cell> {
+        match self.column_height(coordinates) {
+            4 => None,
+            floor => Some(Cell { coordinates, floor })
+        }
     }
 
     pub fn can_play(&self, coordinates: Coordinates) -> bool {
@@ -210,10 +217,7 @@ impl Position {
     }
 
     pub fn is_terminal(&self) -> bool {
-        match self.get_static_evaluation() {
-            Evaluation::HeuristicScore(_) => false,
-            _ => true
-        }
+        !matches!(self.get_static_evaluation(), Evaluation::HeuristicScore(_))
     }
 
     pub fn generate_moves(&self) -> Vec<Coordinates> {
@@ -228,11 +232,13 @@ impl Position {
             }
         }
 
-        playable_coords.sort_by(
-            |a,b| -> Ordering {
-                let position_a = self.play(*a);
-                let position_b = self.play(*b);
-                position_a.get_static_evaluation().cmp(&position_b.get_static_evaluation())
+        let mut scratch = self.clone();
+        playable_coords.sort_by_cached_key(
+            |coords| {
+                scratch.make_move(*coords);
+                let evaluation = scratch.get_static_evaluation();
+                scratch.unmake_move();
+                evaluation
             }
         );
 
@@ -241,31 +247,251 @@ impl Position {
 }
 
 
-#[derive(Clone, Copy, PartialEq)]
-enum Slot {
-    Occupied(Player),
-    Empty
+/// Positions are equal if they have the same pieces on the board and the same player to move, regardless of how
+/// they came about.
+impl PartialEq for Position {
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board && self.active_player == other.active_player && self.num_moves_played == other.num_moves_played
+    }
 }
 
-impl fmt::Display for Slot {
+impl Eq for Position {}
+
+impl fmt::Debug for Position {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Slot::Empty => return write!(f, " "),
-            Slot::Occupied(p) => match p {
-                Player::White => return write!(f, "○"),
-                Player::Black => return write!(f, "●")
+        write!(f, "Position(\"{}\")", self)
+    }
+}
+
+/// Formats the position as a single line, such as "w..............b/................/................/................
+/// w 2". The four floors are given from the bottom up, separated by slashes, with each floor listing its cells
+/// rank by rank from a1 through d1 up to a4 through d4. 'w' and 'b' mark White and Black pieces and '.' an empty
+/// cell. The board is followed by the player to move and the number of moves played.
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for floor in 0..4 {
+            if floor > 0 {
+                write!(f, "/")?;
+            };
+            for rank in 0..4 {
+                for file in 0..4 {
+                    match self.board[floor][file][rank] {
+                        Slot::Empty => write!(f, ".")?,
+                        Slot::Occupied(player) => write!(f, "{}", player)?
+                    };
+                }
+            }
+        }
+
+        write!(f, " {} {}", self.active_player, self.num_moves_played)
+    }
+}
+
+impl FromStr for Position {
+    type Err = ParsePositionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let (board_field, active_player_field, num_moves_field) = match fields[..] {
+            [board_field, active_player_field, num_moves_field] => (board_field, active_player_field, num_moves_field),
+            _ => return Err(ParsePositionError::WrongNumberOfFields(fields.len()))
+        };
+
+        let floors: Vec<&str> = board_field.split('/').collect();
+        if floors.len() != 4 {
+            return Err(ParsePositionError::WrongNumberOfFloors(floors.len()))
+        };
+
+        let mut position = Position::new();
+        let mut num_white_pieces = 0;
+        let mut num_black_pieces = 0;
+
+        for (floor, cells) in floors.iter().enumerate() {
+            let cells: Vec<char> = cells.chars().collect();
+            if cells.len() != 16 {
+                return Err(ParsePositionError::WrongFloorLength { floor, length: cells.len() })
+            };
+
+            for (i, c) in cells.into_iter().enumerate() {
+                let (file, rank) = (i % 4, i / 4);
+                let slot = match c {
+                    '.' => Slot::Empty,
+                    'w' => {
+                        num_white_pieces += 1;
+                        Slot::Occupied(Player::White)
+                    },
+                    'b' => {
+                        num_black_pieces += 1;
+                        Slot::Occupied(Player::Black)
+                    },
+                    c => return Err(ParsePositionError::InvalidCell(c))
+                };
+
+                if slot != Slot::Empty && floor > 0 && position.board[floor-1][file][rank] == Slot::Empty {
+                    return Err(ParsePositionError::FloatingPiece { coordinates: Coordinates::new(file, rank), floor })
+                };
+
+                position.board[floor][file][rank] = slot;
             }
         }
+
+        position.active_player = match active_player_field {
+            "w" => Player::White,
+            "b" => Player::Black,
+            other => return Err(ParsePositionError::InvalidActivePlayer(other.to_string()))
+        };
+
+        let num_moves_played: u8 = num_moves_field.parse().map_err(|_| ParsePositionError::InvalidMoveCount(num_moves_field.to_string()))?;
+        if num_moves_played != num_white_pieces + num_black_pieces {
+            return Err(ParsePositionError::MoveCountMismatch { stated: num_moves_played, pieces: num_white_pieces + num_black_pieces })
+        };
+        position.num_moves_played = num_moves_played;
+
+        // Either player may have moved first, so with equal piece counts either may be to move. Otherwise the player
+        // who is a piece behind must be.
+        let expected_active_player = match num_white_pieces as i16 - num_black_pieces as i16 {
+            0 => None,
+            1 => Some(Player::Black),
+            -1 => Some(Player::White),
+            _ => return Err(ParsePositionError::UnbalancedPieceCounts { white: num_white_pieces, black: num_black_pieces })
+        };
+        if expected_active_player.is_some_and(|player| player != position.active_player) {
+            return Err(ParsePositionError::WrongActivePlayer)
+        };
+
+        // Only the player who has just moved can have completed a line, since the game ends as soon as one is.
+        let winners: Vec<Player> = position.winning_lines().iter().filter_map(|line| match line.state {
+            LineState::Completed { winner } => Some(winner),
+            _ => None
+        }).collect();
+        if winners.contains(&Player::White) && winners.contains(&Player::Black) {
+            return Err(ParsePositionError::BothPlayersCompletedLines)
+        };
+        if winners.contains(&position.active_player) {
+            return Err(ParsePositionError::LineCompletedByPlayerToMove)
+        };
+
+        Ok(position)
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParsePositionError {
+    WrongNumberOfFields(usize),
+    WrongNumberOfFloors(usize),
+    WrongFloorLength { floor: usize, length: usize },
+    InvalidCell(char),
+    FloatingPiece { coordinates: Coordinates, floor: usize },
+    InvalidActivePlayer(String),
+    InvalidMoveCount(String),
+    MoveCountMismatch { stated: u8, pieces: u8 },
+    UnbalancedPieceCounts { white: u8, black: u8 },
+    WrongActivePlayer,
+    LineCompletedByPlayerToMove,
+    BothPlayersCompletedLines
+}
+
+impl fmt::Display for ParsePositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParsePositionError::WrongNumberOfFields(n) => write!(f, "expected 3 space-separated fields (board, player to move, move count) but found {}", n),
+            ParsePositionError::WrongNumberOfFloors(n) => write!(f, "expected 4 floors separated by '/' but found {}", n),
+            ParsePositionError::WrongFloorLength { floor, length } => write!(f, "floor {} has {} cells, expected 16", floor+1, length),
+            ParsePositionError::InvalidCell(c) => write!(f, "invalid cell '{}', expected 'w', 'b' or '.'", c),
+            ParsePositionError::FloatingPiece { coordinates, floor } => write!(f, "the piece at {} on floor {} has nothing beneath it", coordinates, floor+1),
+            ParsePositionError::InvalidActivePlayer(s) => write!(f, "invalid player to move \"{}\", expected 'w' or 'b'", s),
+            ParsePositionError::InvalidMoveCount(s) => write!(f, "invalid move count \"{}\"", s),
+            ParsePositionError::MoveCountMismatch { stated, pieces } => write!(f, "the move count is {} but there are {} pieces on the board", stated, pieces),
+            ParsePositionError::UnbalancedPieceCounts { white, black } => write!(f, "White has {} pieces and Black has {}, which cannot happen in a game", white, black),
+            ParsePositionError::WrongActivePlayer => write!(f, "the player to move does not match the number of pieces each player has"),
+            ParsePositionError::LineCompletedByPlayerToMove => write!(f, "the player to move has already completed a line, so the game ended before their opponent's last move"),
+            ParsePositionError::BothPlayersCompletedLines => write!(f, "both players have completed a line, but the game ends with the first")
+        }
+    }
+}
+
+impl Error for ParsePositionError {}
+
+
+/// Why the engine could not do what it was asked to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineError {
+    /// The column is already full.
+    IllegalMove(Coordinates),
+    /// The coordinates are not on the 4x4 board.
+    OutOfRange { file: usize, rank: usize },
+    /// The floor is not one of the four floors.
+    FloorOutOfRange(usize),
+    /// The game is already over, so there is nothing to play or search.
+    TerminalPosition,
+    /// The move was recorded for this player, but it is the other player's turn.
+    OutOfTurn(Player),
+    InvalidPosition(ParsePositionError)
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::IllegalMove(coordinates) => write!(f, "column {} is full", coordinates),
+            EngineError::OutOfRange { file, rank } => write!(f, "file {} and rank {} are off the board, expected 0-3", file, rank),
+            EngineError::FloorOutOfRange(floor) => write!(f, "floor {} is off the board, expected 0-3", floor),
+            EngineError::TerminalPosition => write!(f, "the game is already over"),
+            EngineError::OutOfTurn(player) => write!(f, "the move was recorded for {:?}, but it is not their turn", player),
+            EngineError::InvalidPosition(e) => write!(f, "invalid position: {}", e)
+        }
     }
 }
 
+impl Error for EngineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EngineError::InvalidPosition(e) => Some(e),
+            _ => None
+        }
+    }
+}
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+impl From<ParsePositionError> for EngineError {
+    fn from(e: ParsePositionError) -> Self {
+        EngineError::InvalidPosition(e)
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+enum Slot {
+    Occupied(Player),
+    Empty
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Player {
     White,
     Black
 }
 
+impl Neg for Player {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        match self {
+            Player::White => Player::Black,
+            Player::Black => Player::White
+        }
+    }
+}
+
+/// Writes the letter that stands for the player in position strings and records: 'w' or 'b'.
+impl fmt::Display for Player {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Player::White => write!(f, "w"),
+            Player::Black => write!(f, "b")
+        }
+    }
+}
+
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Evaluation {
@@ -276,13 +502,13 @@ pub enum Evaluation {
 }
 
 impl Evaluation {
-    pub fn to_confidence(&self) -> f32 {
+    pub fn to_confidence(self) -> f32 {
         match self {
             Evaluation::Win => 1.0,
             Evaluation::Loss => 0.0,
             Evaluation::Draw => 0.5,
             Evaluation::HeuristicScore(s) => {
-                let exponent = *s as f32 / 5.0;
+                let exponent = s as f32 / 5.0;
                 let base: f32 = 2.0;
                 let denominator = 1.0 + base.powf(-exponent);
                 1.0 / denominator
@@ -304,6 +530,18 @@ impl Neg for Evaluation {
     }
 }
 
+/// Formats the evaluation as "win", "loss", "draw" or a signed heuristic score such as "+3".
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Evaluation::Win => write!(f, "win"),
+            Evaluation::Loss => write!(f, "loss"),
+            Evaluation::Draw => write!(f, "draw"),
+            Evaluation::HeuristicScore(s) => write!(f, "{:+}", s)
+        }
+    }
+}
+
 impl PartialOrd for Evaluation {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -331,7 +569,7 @@ impl Ord for Evaluation {
                         Ordering::Equal
                     }
                 },
-                _ => panic!("Something went wrong.")
+                Evaluation::Draw => Ordering::Equal
             },
             Evaluation::HeuristicScore(s) => match other {
                 Evaluation::Win => Ordering::Less,
@@ -352,10 +590,16 @@ impl Ord for Evaluation {
 }
 
 
-enum LineState {
+/// How far a line has got towards being completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineState {
+    /// No pieces are on the line yet.
     Uncontrolled,
+    /// Only one player has pieces on the line, `degree` of them, so it can still be completed.
     Controlled { controlling_player: Player, degree: i8 },
-    Completed,
+    /// One player has all four cells, winning the game.
+    Completed { winner: Player },
+    /// Both players have pieces on the line, so neither can complete it.
     Plugged
 }
 
@@ -371,42 +615,231 @@ impl AddAssign<Slot> for LineState {
             LineState::Controlled { controlling_player, degree } => match *controlling_player == player {
                 true => {
                     if *degree == 3 {
-                        *self = LineState::Completed
+                        *self = LineState::Completed { winner: player }
                     } else {
                         *self = LineState::Controlled { controlling_player: *controlling_player, degree: *degree+1 }
                     }
                 },
                 false => *self = LineState::Plugged
             },
-            LineState::Plugged => return,
-            LineState::Completed => panic!("You can't add slots to a completed line.")
+            LineState::Plugged | LineState::Completed { .. } => ()
+        }
+    }
+}
+
+
+/// One of the 76 lines of four cells on the board, with its state in a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Line {
+    pub cells: [Cell; 4],
+    pub state: LineState
+}
+
+
+/// Every line on the board: the 48 along files, ranks and columns, the 24 diagonals within a single file, rank or
+/// floor, and the 4 diagonals between opposite corners of the cube.
+static LINES: [[Cell; 4]; 76] = find_lines();
+
+const fn find_lines() -> [[Cell; 4]; 76] {
+    let mut lines = [[Cell { coordinates: Coordinates { file: 0, rank: 0 }, floor: 0 }; 4]; 76];
+    let mut num_lines = 0;
+
+    // Each direction is a step of -1, 0 or 1 along the file, rank and floor. A line is found from the end at which
+    // the first step that is not 0 is positive, so that it is found only once.
+    let mut direction = 0;
+    while direction < 27 {
+        let step: [isize; 3] = [direction / 9 - 1, direction / 3 % 3 - 1, direction % 3 - 1];
+        let first_step = if step[0] != 0 { step[0] } else if step[1] != 0 { step[1] } else { step[2] };
+
+        let mut start = 0;
+        while first_step == 1 && start < 64 {
+            // A line runs right across the board along every axis it moves along.
+            let origin: [isize; 3] = [start / 16, start / 4 % 4, start % 4];
+            let mut is_end = true;
+            let mut axis = 0;
+            while axis < 3 {
+                if (step[axis] == 1 && origin[axis] != 0) || (step[axis] == -1 && origin[axis] != 3) {
+                    is_end = false;
+                };
+                axis += 1;
+            }
+
+            let mut i = 0;
+            while is_end && i < 4 {
+                let coordinates = Coordinates { file: (origin[0] + i*step[0]) as usize, rank: (origin[1] + i*step[1]) as usize };
+                lines[num_lines][i as usize] = Cell { coordinates, floor: (origin[2] + i*step[2]) as usize };
+                i += 1;
+            }
+            if is_end {
+                num_lines += 1;
+            };
+
+            start += 1;
         }
+
+        direction += 1;
     }
+
+    lines
 }
 
 
-#[derive(Clone, Copy)]
+/// The column a piece is dropped into, always on the board: both the file and the rank are 0-3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Coordinates {
-    pub file: usize,
-    pub rank: usize
+    file: usize,
+    rank: usize
 }
 
 impl Coordinates {
+    /// # Panics
+    ///
+    /// Panics if the file or rank is not 0-3; see `try_new` for a version that does not.
     pub fn new(file: usize, rank: usize) -> Self {
-        Coordinates { file, rank }
+        match Coordinates::try_new(file, rank) {
+            Ok(coordinates) => coordinates,
+            Err(e) => panic!("{}", e)
+        }
+    }
+
+    /// Returns the coordinates with the given file and rank, or an error if either is not 0-3.
+    pub fn try_new(file: usize, rank: usize) -> Result<Self, EngineError> {
+        if file >= 4 || rank >= 4 {
+            return Err(EngineError::OutOfRange { file, rank })
+        };
+
+        Ok(Coordinates { file, rank })
+    }
+
+    pub fn file(&self) -> usize {
+        self.file
+    }
+
+    pub fn rank(&self) -> usize {
+        self.rank
+    }
+}
+
+impl Coordinates {
+    /// Parses a move in notation, returning the coordinates along with the floor annotation if one was given. Files
+    /// are lettered a-d and ranks numbered 1-4, so that "b3" is file 2, rank 3. An optional floor suffix such as
+    /// "b3:2" records which floor (1-4) the piece landed on.
+    pub fn parse_with_floor(s: &str) -> Result<(Coordinates, Option<usize>), ParseCoordinatesError> {
+        let (square, floor) = match s.split_once(':') {
+            Some((square, floor)) => (square, Some(floor)),
+            None => (s, None)
+        };
+
+        let mut chars = square.chars();
+        let (file_char, rank_char) = match (chars.next(), chars.next(), chars.next()) {
+            (Some(file_char), Some(rank_char), None) => (file_char, rank_char),
+            _ => return Err(ParseCoordinatesError::Malformed(s.to_string()))
+        };
+
+        let file = match file_char.to_ascii_lowercase() {
+            c @ 'a'..='d' => c as usize - 'a' as usize,
+            c => return Err(ParseCoordinatesError::InvalidFile(c))
+        };
+
+        let rank = match rank_char {
+            c @ '1'..='4' => c as usize - '1' as usize,
+            c => return Err(ParseCoordinatesError::InvalidRank(c))
+        };
+
+        let floor = match floor {
+            None => None,
+            Some(f @ ("1" | "2" | "3" | "4")) => Some(f.parse::<usize>().unwrap() - 1),
+            Some(f) => return Err(ParseCoordinatesError::InvalidFloor(f.to_string()))
+        };
+
+        Ok((Coordinates::new(file, rank), floor))
     }
 }
 
 impl fmt::Display for Coordinates {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "({}, {})", self.file+1, self.rank+1)
+        write!(f, "{}{}", (b'a' + self.file as u8) as char, self.rank+1)
+    }
+}
+
+impl FromStr for Coordinates {
+    type Err = ParseCoordinatesError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Coordinates::parse_with_floor(s).map(|(coordinates, _)| coordinates)
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseCoordinatesError {
+    Malformed(String),
+    InvalidFile(char),
+    InvalidRank(char),
+    InvalidFloor(String)
+}
+
+impl fmt::Display for ParseCoordinatesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseCoordinatesError::Malformed(s) => write!(f, "\"{}\" is not a move, expected something like \"b3\"", s),
+            ParseCoordinatesError::InvalidFile(c) => write!(f, "invalid file '{}', expected a-d", c),
+            ParseCoordinatesError::InvalidRank(c) => write!(f, "invalid rank '{}', expected 1-4", c),
+            ParseCoordinatesError::InvalidFloor(s) => write!(f, "invalid floor \"{}\", expected 1-4", s)
+        }
+    }
+}
+
+impl Error for ParseCoordinatesError {}
+
+
+/// A single slot on the board: a column and a floor, numbered 0-3 from the bottom up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    coordinates: Coordinates,
+    floor: usize
+}
+
+impl Cell {
+    /// # Panics
+    ///
+    /// Panics if the floor is not 0-3; see `try_new` for a version that does not.
+    pub fn new(coordinates: Coordinates, floor: usize) -> Self {
+        match Cell::try_new(coordinates, floor) {
+            Ok(cell) => cell,
+            Err(e) => panic!("{}", e)
+        }
+    }
+
+    /// Returns the cell on the given floor of the column, or an error if the floor is not 0-3.
+    pub fn try_new(coordinates: Coordinates, floor: usize) -> Result<Self, EngineError> {
+        if floor >= 4 {
+            return Err(EngineError::FloorOutOfRange(floor))
+        };
+
+        Ok(Cell { coordinates, floor })
+    }
+
+    pub fn coordinates(&self) -> Coordinates {
+        self.coordinates
+    }
+
+    pub fn floor(&self) -> usize {
+        self.floor
+    }
+}
+
+/// Formats the cell in move notation with its floor, such as "b3:2".
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.coordinates, self.floor+1)
     }
 }
 
 
 #[cfg(test)]
 mod tests {
-    use super::{Position, Coordinates, Evaluation, Player};
+    use super::{Position, Coordinates, Cell, Evaluation, Player, EngineError, LineState, ParseCoordinatesError, ParsePositionError};
 
     #[test]
     fn test_position_evolution() {
@@ -426,4 +859,139 @@ mod tests {
         assert_eq!(position.get_static_evaluation(), Evaluation::HeuristicScore(-2));
         assert_eq!(position.active_player, Player::White);
     }
+
+    #[test]
+    fn test_make_and_unmake_move() {
+        let mut position = Position::new();
+
+        position.make_move(Coordinates::new(1, 2));
+        position.make_move(Coordinates::new(1, 2));
+        position.make_move(Coordinates::new(3, 0));
+
+        assert_eq!(position.unmake_move(), Some(Coordinates::new(3, 0)));
+        assert_eq!(position.unmake_move(), Some(Coordinates::new(1, 2)));
+        assert_eq!(position.active_player, Player::Black);
+        assert_eq!(position.get_static_evaluation(), Position::new().play(Coordinates::new(1, 2)).get_static_evaluation());
+
+        assert_eq!(position.unmake_move(), Some(Coordinates::new(1, 2)));
+        assert_eq!(position.unmake_move(), None);
+        assert_eq!(position.get_static_evaluation(), Evaluation::HeuristicScore(0));
+    }
+
+    #[test]
+    fn test_coordinates_notation() {
+        assert_eq!("a1".parse(), Ok(Coordinates::new(0, 0)));
+        assert_eq!("D4".parse(), Ok(Coordinates::new(3, 3)));
+        assert_eq!("b3:2".parse(), Ok(Coordinates::new(1, 2)));
+        assert_eq!(Coordinates::parse_with_floor("c2:4"), Ok((Coordinates::new(2, 1), Some(3))));
+        assert_eq!(Coordinates::new(2, 0).to_string(), "c1");
+
+        assert_eq!("e1".parse::<Coordinates>(), Err(ParseCoordinatesError::InvalidFile('e')));
+        assert_eq!("a5".parse::<Coordinates>(), Err(ParseCoordinatesError::InvalidRank('5')));
+        assert_eq!("a1:0".parse::<Coordinates>(), Err(ParseCoordinatesError::InvalidFloor("0".to_string())));
+        assert_eq!("a12".parse::<Coordinates>(), Err(ParseCoordinatesError::Malformed("a12".to_string())));
+        assert_eq!("".parse::<Coordinates>(), Err(ParseCoordinatesError::Malformed("".to_string())));
+    }
+
+    #[test]
+    fn test_position_string() {
+        let empty = "................/................/................/................ w 0";
+        assert_eq!(Position::new().to_string(), empty);
+        assert_eq!(empty.parse(), Ok(Position::new()));
+        assert_eq!("................/................/................/................ b 0".parse(), Ok(Position::starting_with(Player::Black)));
+        assert_eq!(".b............../................/................/................ w 1".parse(), Ok(Position::starting_with(Player::Black).play(Coordinates::new(1, 0))));
+
+        let position = Position::new().play(Coordinates::new(1, 2)).play(Coordinates::new(1, 2)).play(Coordinates::new(3, 0));
+        let position_string = "...w.....w....../.........b....../................/................ b 3";
+        assert_eq!(position.to_string(), position_string);
+        assert_eq!(position_string.parse(), Ok(position));
+
+        assert_eq!("................/................/................ w 0".parse::<Position>(), Err(ParsePositionError::WrongNumberOfFloors(3)));
+        assert_eq!("w.............../................/................/........w....... w 0".parse::<Position>(), Err(ParsePositionError::FloatingPiece { coordinates: Coordinates::new(0, 2), floor: 3 }));
+        assert_eq!("w.............../................/................/................ w 1".parse::<Position>(), Err(ParsePositionError::WrongActivePlayer));
+        assert_eq!("w.............../................/................/................ b 2".parse::<Position>(), Err(ParsePositionError::MoveCountMismatch { stated: 2, pieces: 1 }));
+        assert_eq!("ww............../................/................/................ b 2".parse::<Position>(), Err(ParsePositionError::UnbalancedPieceCounts { white: 2, black: 0 }));
+        assert_eq!("x.............../................/................/................ b 1".parse::<Position>(), Err(ParsePositionError::InvalidCell('x')));
+
+        assert!("wwwwbbb........./................/................/................ b 7".parse::<Position>().is_ok());
+        assert_eq!("wwwwbbb.b......./................/................/................ w 8".parse::<Position>(), Err(ParsePositionError::LineCompletedByPlayerToMove));
+        assert_eq!("wwwwbbbb......../................/................/................ w 8".parse::<Position>(), Err(ParsePositionError::BothPlayersCompletedLines));
+    }
+
+    #[test]
+    fn test_try_play() {
+        let mut position = Position::new();
+        for _ in 0..4 {
+            position = position.try_play(Coordinates::new(1, 2)).unwrap();
+        }
+
+        assert_eq!(position.try_play(Coordinates::new(1, 2)).err(), Some(EngineError::IllegalMove(Coordinates::new(1, 2))));
+
+        for _ in 0..3 {
+            position = position.play(Coordinates::new(0, 0)).play(Coordinates::new(3, 3));
+        }
+        let position = position.play(Coordinates::new(0, 0));
+        assert_eq!(position.try_play(Coordinates::new(2, 2)).err(), Some(EngineError::TerminalPosition));
+
+        let error: EngineError = "nonsense".parse::<Position>().unwrap_err().into();
+        assert_eq!(error, EngineError::InvalidPosition(ParsePositionError::WrongNumberOfFields(1)));
+    }
+
+    #[test]
+    fn test_cells() {
+        assert_eq!(Coordinates::try_new(4, 0), Err(EngineError::OutOfRange { file: 4, rank: 0 }));
+        assert_eq!(Cell::try_new(Coordinates::new(1, 2), 4), Err(EngineError::FloorOutOfRange(4)));
+        assert_eq!(Cell::new(Coordinates::new(1, 2), 1).to_string(), "b3:2");
+
+        let b3 = Coordinates::new(1, 2);
+        let position = Position::new().play(b3).play(b3);
+        assert_eq!(position.piece_at(Cell::new(b3, 0)), Some(Player::White));
+        assert_eq!(position.piece_at(Cell::new(b3, 1)), Some(Player::Black));
+        assert_eq!(position.piece_at(Cell::new(b3, 2)), None);
+        assert_eq!(position.landing_cell(b3), Some(Cell::new(b3, 2)));
+        assert_eq!(position.last_move(), Some(Cell::new(b3, 1)));
+        assert_eq!(Position::new().last_move(), None);
+        assert_eq!(position.play(b3).play(b3).landing_cell(b3), None);
+    }
+
+    #[test]
+    fn test_winning_lines() {
+        let mut position = Position::new();
+        for _ in 0..3 {
+            position = position.play(Coordinates::new(0, 0)).play(Coordinates::new(3, 3));
+        }
+        assert!(position.winning_lines().is_empty());
+
+        let position = position.play(Coordinates::new(0, 0));
+        let winning_lines = position.winning_lines();
+        assert_eq!(winning_lines.len(), 1);
+        assert_eq!(winning_lines[0].state, LineState::Completed { winner: Player::White });
+        assert!((0..4).all(|floor| winning_lines[0].cells.contains(&Cell::new(Coordinates::new(0, 0), floor))));
+    }
+
+    #[test]
+    fn test_board_queries() {
+        let mut lines: Vec<Vec<(usize, usize, usize)>> = Position::new().lines().map(|line| {
+            assert_eq!(line.state, LineState::Uncontrolled);
+            let mut cells: Vec<_> = line.cells.iter().map(|cell| (cell.floor(), cell.coordinates().file(), cell.coordinates().rank())).collect();
+            cells.sort();
+            cells
+        }).collect();
+        lines.sort();
+        lines.dedup();
+        assert_eq!(lines.len(), 76);
+
+        // White's a1 and b2 share the long diagonal, and Black's b2 plugs the b2 column and a diagonal of the bottom
+        // floor, leaving White 10 lines with one piece on them.
+        let b2 = Coordinates::new(1, 1);
+        let position = Position::new().play(Coordinates::new(0, 0)).play(b2).play(b2);
+        let count = |state| position.lines().filter(|line| line.state == state).count();
+        assert_eq!(count(LineState::Controlled { controlling_player: Player::White, degree: 1 }), 10);
+        assert_eq!(count(LineState::Controlled { controlling_player: Player::White, degree: 2 }), 1);
+        assert_eq!(count(LineState::Plugged), 2);
+
+        assert_eq!(position.num_moves_played(), 3);
+        assert_eq!(position.pieces(Player::White), vec![Cell::new(Coordinates::new(0, 0), 0), Cell::new(b2, 1)]);
+        assert_eq!(position.pieces(Player::Black), vec![Cell::new(b2, 0)]);
+    }
 }
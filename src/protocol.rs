@@ -0,0 +1,264 @@
+use yonmokun::evaluation::{self, AnalysisReport, SearchLimits, StopHandle, MAX_DEPTH};
+use yonmokun::position::{Position, Coordinates};
+use std::io::{self, BufRead, Write};
+use std::sync::Mutex;
+use std::thread::{self, ScopedJoinHandle};
+use std::time::Duration;
+
+
+/// Runs the text engine protocol, reading commands from `reader` one per line and writing responses to `writer`,
+/// until `quit` or the end of input. The protocol is modelled on UCI:
+///
+/// - `protocol` identifies the engine, answering with `id` lines and `protocolok`.
+/// - `isready` answers `readyok`, even while a search is running.
+/// - `newgame` goes back to the starting position.
+/// - `position startpos [moves <move>...]` or `position string <position string> [moves <move>...]` sets the
+///   position.
+/// - `play <move>` plays a move in the current position.
+/// - `go [depth <plies>] [movetime <ms>] [threads <n>] [infinite]` searches the current position in the background,
+///   reporting with an `info` line each time it finishes searching to a new depth and then with `bestmove <move>`.
+///   Limits not given are taken from `default_limits`, and `infinite` searches until stopped.
+/// - `stop` stops the running search, which then reports the deepest result it finished.
+/// - `show` prints the current position string, and `eval` its static evaluation.
+/// - `quit` stops the engine.
+///
+/// While a search is running, `newgame`, `position`, `play` and `go` stop it before going ahead. Other commands wait
+/// for it to finish, unless it was started with `infinite`, in which case they are answered with
+/// `error search in progress`. The end of input stops an `infinite` search too.
+///
+/// Anything that cannot be carried out is answered with an `error` line and otherwise ignored.
+pub fn run<R: BufRead, W: Write + Send>(reader: R, writer: W, default_limits: SearchLimits) -> io::Result<()> {
+    let writer = Mutex::new(writer);
+
+    thread::scope(|scope| {
+        let mut position = Position::new();
+        let mut search: Option<(ScopedJoinHandle<io::Result<()>>, StopHandle, bool)> = None;
+
+        for line in reader.lines() {
+            let line = line?;
+            let mut tokens = line.split_whitespace();
+            let Some(command) = tokens.next() else {
+                continue;
+            };
+            let arguments: Vec<&str> = tokens.collect();
+
+            // A search that only ends when stopped is never waited for, or the engine would stop responding.
+            let mut busy = false;
+            if let Some((handle, stop, until_stopped)) = search.take() {
+                match command {
+                    "isready" | "protocol" => search = Some((handle, stop, until_stopped)),
+                    "stop" | "quit" | "newgame" | "position" | "play" | "go" => {
+                        stop.stop();
+                        handle.join().unwrap()?;
+                    },
+                    _ if until_stopped => {
+                        search = Some((handle, stop, until_stopped));
+                        busy = true;
+                    },
+                    _ => handle.join().unwrap()?
+                }
+            };
+
+            let response = match command {
+                _ if busy => Err("search in progress".to_string()),
+                "protocol" => Ok(format!("id name yonmokun\nid version {}\nprotocolok", env!("CARGO_PKG_VERSION"))),
+                "isready" => Ok("readyok".to_string()),
+                "newgame" => {
+                    position = Position::new();
+                    Ok(String::new())
+                },
+                "position" => parse_position(&arguments).map(|new_position| {
+                    position = new_position;
+                    String::new()
+                }),
+                "play" => match arguments[..] {
+                    [notation] => play_moves(&mut position, &[notation]).map(|_| String::new()),
+                    _ => Err("play takes one move".to_string())
+                },
+                "go" => match parse_limits(&arguments, default_limits) {
+                    Ok(_) if position.is_terminal() => Err("the game is already over".to_string()),
+                    Ok(limits) => {
+                        let stop = StopHandle::new();
+                        let handle = {
+                            let (position, stop, writer) = (position.clone(), stop.clone(), &writer);
+                            scope.spawn(move || {
+                                let mut result = Ok(());
+                                let report = evaluation::analyse_with_progress(&position, limits, &stop, &mut |progress| {
+                                    if result.is_ok() {
+                                        let mut writer = writer.lock().unwrap();
+                                        result = writeln!(writer, "{}", info_line(progress)).and_then(|_| writer.flush());
+                                    };
+                                });
+                                result?;
+                                let report = report.map_err(io::Error::other)?;
+
+                                // A search stopped before finishing even one ply has not reported anything yet.
+                                let mut writer = writer.lock().unwrap();
+                                if report.depth == 0 {
+                                    writeln!(writer, "{}", info_line(&report))?;
+                                };
+                                writeln!(writer, "bestmove {}", report.best_move)?;
+                                writer.flush()
+                            })
+                        };
+                        search = Some((handle, stop, limits.time_budget == Some(Duration::MAX)));
+                        Ok(String::new())
+                    },
+                    Err(e) => Err(e)
+                },
+                "stop" => Ok(String::new()),
+                "show" => Ok(position.to_string()),
+                "eval" => Ok(format!("eval {}", position.get_static_evaluation())),
+                "quit" => break,
+                _ => Err(format!("unknown command \"{}\"", command))
+            };
+
+            let mut writer = writer.lock().unwrap();
+            match response {
+                Ok(response) if response.is_empty() => {},
+                Ok(response) => writeln!(writer, "{}", response)?,
+                Err(e) => writeln!(writer, "error {}", e)?
+            };
+            writer.flush()?;
+        }
+
+        match search {
+            Some((handle, stop, until_stopped)) => {
+                if until_stopped {
+                    stop.stop();
+                };
+                handle.join().unwrap()
+            },
+            None => Ok(())
+        }
+    })
+}
+
+
+fn info_line(report: &AnalysisReport) -> String {
+    let principal_variation: Vec<String> = report.principal_variation.iter().map(|coordinates| coordinates.to_string()).collect();
+    format!(
+        "info depth {} score {} nodes {} time {} pv {}",
+        report.depth,
+        report.evaluation,
+        report.num_positions_traversed,
+        report.search_time.as_millis(),
+        principal_variation.join(" ")
+    )
+}
+
+
+fn parse_position(arguments: &[&str]) -> Result<Position, String> {
+    let (mut position, rest) = match arguments {
+        ["startpos", rest @ ..] => (Position::new(), rest),
+        ["string", board, active_player, move_count, rest @ ..] => {
+            let position = format!("{} {} {}", board, active_player, move_count).parse::<Position>().map_err(|e| e.to_string())?;
+            (position, rest)
+        },
+        _ => return Err("expected \"position startpos\" or \"position string <position string>\"".to_string())
+    };
+
+    match rest {
+        [] => {},
+        ["moves", moves @ ..] => play_moves(&mut position, moves)?,
+        _ => return Err("expected \"moves\" after the position".to_string())
+    };
+
+    Ok(position)
+}
+
+
+fn play_moves(position: &mut Position, moves: &[&str]) -> Result<(), String> {
+    for notation in moves {
+        let (coordinates, floor) = Coordinates::parse_with_floor(notation).map_err(|e| e.to_string())?;
+        if position.is_terminal() {
+            return Err("the game is already over".to_string())
+        };
+        let Some(landing_cell) = position.landing_cell(coordinates) else {
+            return Err(format!("column {} is full", coordinates))
+        };
+        if floor.is_some_and(|floor| floor != landing_cell.floor()) {
+            return Err(format!("{} lands on floor {}", coordinates, landing_cell.floor()+1))
+        };
+        position.make_move(coordinates);
+    }
+
+    Ok(())
+}
+
+
+fn parse_limits(arguments: &[&str], default_limits: SearchLimits) -> Result<SearchLimits, String> {
+    let mut limits = default_limits;
+    let mut depth = None;
+    let mut arguments = arguments.iter();
+
+    while let Some(name) = arguments.next() {
+        if *name == "infinite" {
+            limits.time_budget = Some(Duration::MAX);
+            continue;
+        };
+
+        let value = arguments.next().ok_or_else(|| format!("{} needs a value", name))?;
+        let invalid = || format!("invalid {} \"{}\"", name, value);
+
+        match *name {
+            "depth" => depth = Some(value.parse().ok().filter(|depth| *depth > 0).ok_or_else(invalid)?),
+            "movetime" => limits.time_budget = Some(Duration::from_millis(value.parse().map_err(|_| invalid())?)),
+            "threads" => limits.threads = value.parse().ok().filter(|threads| *threads > 0).ok_or_else(invalid)?,
+            _ => return Err(format!("unknown search limit \"{}\"", name))
+        }
+    }
+
+    // Given only a time limit, the search deepens for as long as it allows.
+    limits.depth = match (depth, limits.time_budget) {
+        (Some(depth), _) => depth,
+        (None, Some(_)) => MAX_DEPTH,
+        (None, None) => default_limits.depth
+    };
+
+    Ok(limits)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::run;
+    use yonmokun::evaluation::SearchLimits;
+
+    fn respond(input: &str) -> Vec<String> {
+        let mut output = Vec::new();
+        run(input.as_bytes(), &mut output, SearchLimits { depth: 2, time_budget: None, threads: 1 }).unwrap();
+        String::from_utf8(output).unwrap().lines().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_protocol() {
+        let output = respond("protocol\nisready\nposition startpos moves a1 d4 a1 d4 a1 d4\ngo depth 3\nshow\n");
+        assert_eq!(output[0], "id name yonmokun");
+        assert_eq!(output[2], "protocolok");
+        assert_eq!(output[3], "readyok");
+        assert!(output[4].starts_with("info depth 1 score win "));
+        assert!(output[6].starts_with("info depth 3 score win "));
+        assert_eq!(output[7], "bestmove a1");
+        assert_eq!(output[8], "w..............b/w..............b/w..............b/................ w 6");
+
+        let output = respond("go infinite\nisready\nstop\n");
+        assert_eq!(output[0], "readyok");
+        assert!(output[1].starts_with("info depth "));
+        assert!(output.last().unwrap().starts_with("bestmove "));
+
+        let output = respond("go infinite\n");
+        assert!(output.last().unwrap().starts_with("bestmove "));
+
+        let output = respond("go infinite\neval\nstop\nposition startpos moves a1\ngo infinite\nposition startpos\nshow\n");
+        assert!(output.contains(&"error search in progress".to_string()));
+        assert_eq!(output.iter().filter(|line| line.starts_with("bestmove ")).count(), 2);
+        assert_eq!(output.last().unwrap(), "................/................/................/................ w 0");
+
+        let output = respond("play a1\nplay b7\nposition string nonsense\nfrobnicate\neval\n");
+        assert!(output[0].starts_with("error invalid rank"));
+        assert!(output[1].starts_with("error expected"));
+        assert_eq!(output[2], "error unknown command \"frobnicate\"");
+        assert_eq!(output[3], "eval -7");
+    }
+}
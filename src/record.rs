@@ -0,0 +1,288 @@
+use crate::game::{Game, GameResult, MoveRecord, Participant};
+use crate::position::{Position, Coordinates, Evaluation, Player, ParseCoordinatesError, ParsePositionError};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+
+/// Free-form settings saved alongside a game, such as who was playing each side.
+pub type Settings = BTreeMap<String, String>;
+
+
+pub struct SavedGame {
+    pub game: Game,
+    pub settings: Settings
+}
+
+
+/// Saves a single game to a file, replacing anything already there.
+pub fn save(path: &Path, game: &Game, settings: &Settings) -> Result<(), RecordError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_game(&mut writer, game, settings)?;
+    writer.flush()?;
+    Ok(())
+}
+
+
+/// Loads every game in a file.
+pub fn load(path: &Path) -> Result<Vec<SavedGame>, RecordError> {
+    read_games(BufReader::new(File::open(path)?))
+}
+
+
+/// Writes a game in the record format. A record looks like this:
+///
+/// ```text
+/// Game
+/// Setting auto_play false
+/// Setting black engine 4
+/// Setting hints false
+/// Setting white human
+/// Start ................/................/................/................ w 0
+/// Move a1 w human 1697712345123 ?
+/// Move d4 b engine 1697712346456 +3
+/// Result ongoing
+/// End
+/// ```
+///
+/// Each move gives its notation, the player who made it, who was at the controls, when it was played in
+/// milliseconds since the Unix epoch ("-" if not known) and the evaluation from the mover's point of view ("win", "loss", "draw", a
+/// signed heuristic score or "?" if none was recorded). The result is "w", "b", "draw" or "ongoing". Any number of
+/// records can follow one another in the same file, and lines starting with '#' are ignored.
+pub fn write_game<W: Write>(writer: &mut W, game: &Game, settings: &Settings) -> io::Result<()> {
+    writeln!(writer, "Game")?;
+
+    for (key, value) in settings {
+        writeln!(writer, "Setting {} {}", key, value)?;
+    }
+
+    writeln!(writer, "Start {}", game.start_position())?;
+
+    for record in game.moves() {
+        let timestamp = match record.timestamp {
+            Some(timestamp) => timestamp.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis().to_string(),
+            None => "-".to_string()
+        };
+        writeln!(writer, "Move {} {} {} {} {}", record.coordinates, record.player, participant_to_str(record.participant), timestamp, evaluation_to_str(record.evaluation))?;
+    }
+
+    let result = match game.result() {
+        Some(GameResult::Win(player)) => player.to_string(),
+        Some(GameResult::Draw) => "draw".to_string(),
+        None => "ongoing".to_string()
+    };
+    writeln!(writer, "Result {}", result)?;
+
+    writeln!(writer, "End")
+}
+
+
+/// Reads every game record from a reader. See `write_game` for the format.
+pub fn read_games<R: BufRead>(reader: R) -> Result<Vec<SavedGame>, RecordError> {
+    let mut saved_games = Vec::new();
+    let mut current: Option<SavedGame> = None;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line_number = i+1;
+        let line = line?;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        };
+
+        let malformed = |reason: &str| RecordError::Malformed { line: line_number, reason: reason.to_string() };
+
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+
+        if keyword == "Game" {
+            if current.is_some() {
+                return Err(malformed("a new game started before the previous one ended"))
+            };
+            current = Some(SavedGame { game: Game::new(), settings: Settings::new() });
+            continue;
+        };
+
+        let saved_game = match current.as_mut() {
+            Some(saved_game) => saved_game,
+            None => return Err(malformed("expected \"Game\" to start a record"))
+        };
+
+        match keyword {
+            "Setting" => {
+                let (key, value) = rest.split_once(' ').unwrap_or((rest, ""));
+                saved_game.settings.insert(key.to_string(), value.to_string());
+            },
+            "Start" => {
+                if !saved_game.game.moves().is_empty() {
+                    return Err(malformed("the start position must come before the moves"))
+                };
+                let position: Position = rest.parse().map_err(|e| RecordError::InvalidPosition { line: line_number, error: e })?;
+                saved_game.game = Game::from_position(position);
+            },
+            "Move" => {
+                let fields: Vec<&str> = rest.split_whitespace().collect();
+                if fields.len() != 5 {
+                    return Err(malformed("expected a move, player, participant, timestamp and evaluation"))
+                };
+
+                let coordinates: Coordinates = fields[0].parse().map_err(|e| RecordError::InvalidMove { line: line_number, error: e })?;
+                let player = player_from_str(fields[1]).ok_or_else(|| malformed("invalid player, expected \"w\" or \"b\""))?;
+                let participant = participant_from_str(fields[2]).ok_or_else(|| malformed("invalid participant, expected \"human\", \"engine\" or \"unknown\""))?;
+                let timestamp = match fields[3] {
+                    "-" => None,
+                    millis => Some(UNIX_EPOCH + Duration::from_millis(millis.parse().map_err(|_| malformed("invalid timestamp"))?))
+                };
+                let evaluation = evaluation_from_str(fields[4]).ok_or_else(|| malformed("invalid evaluation"))?;
+
+                saved_game.game.play_record(MoveRecord {
+                    coordinates,
+                    player,
+                    participant,
+                    timestamp,
+                    evaluation
+                }).map_err(|_| RecordError::IllegalMove { line: line_number, coordinates })?;
+            },
+            "Result" => {
+                let expected = match saved_game.game.result() {
+                    Some(GameResult::Win(player)) => player.to_string(),
+                    Some(GameResult::Draw) => "draw".to_string(),
+                    None => "ongoing".to_string()
+                };
+                if rest != expected {
+                    return Err(malformed("the result does not match the moves played"))
+                };
+            },
+            "End" => saved_games.push(current.take().unwrap()),
+            _ => return Err(malformed("unknown keyword"))
+        }
+    }
+
+    if current.is_some() {
+        return Err(RecordError::UnexpectedEndOfFile)
+    };
+
+    Ok(saved_games)
+}
+
+
+fn player_from_str(s: &str) -> Option<Player> {
+    match s {
+        "w" => Some(Player::White),
+        "b" => Some(Player::Black),
+        _ => None
+    }
+}
+
+fn participant_to_str(participant: Participant) -> &'static str {
+    match participant {
+        Participant::Human => "human",
+        Participant::Engine => "engine",
+        Participant::Unknown => "unknown"
+    }
+}
+
+fn participant_from_str(s: &str) -> Option<Participant> {
+    match s {
+        "human" => Some(Participant::Human),
+        "engine" => Some(Participant::Engine),
+        "unknown" => Some(Participant::Unknown),
+        _ => None
+    }
+}
+
+fn evaluation_to_str(evaluation: Option<Evaluation>) -> String {
+    match evaluation {
+        None => "?".to_string(),
+        Some(evaluation) => evaluation.to_string()
+    }
+}
+
+fn evaluation_from_str(s: &str) -> Option<Option<Evaluation>> {
+    match s {
+        "?" => Some(None),
+        "win" => Some(Some(Evaluation::Win)),
+        "loss" => Some(Some(Evaluation::Loss)),
+        "draw" => Some(Some(Evaluation::Draw)),
+        s => s.parse().ok().map(|score| Some(Evaluation::HeuristicScore(score)))
+    }
+}
+
+
+#[derive(Debug)]
+pub enum RecordError {
+    Io(io::Error),
+    Malformed { line: usize, reason: String },
+    InvalidPosition { line: usize, error: ParsePositionError },
+    InvalidMove { line: usize, error: ParseCoordinatesError },
+    IllegalMove { line: usize, coordinates: Coordinates },
+    UnexpectedEndOfFile
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::Io(e) => write!(f, "{}", e),
+            RecordError::Malformed { line, reason } => write!(f, "line {}: {}", line, reason),
+            RecordError::InvalidPosition { line, error } => write!(f, "line {}: {}", line, error),
+            RecordError::InvalidMove { line, error } => write!(f, "line {}: {}", line, error),
+            RecordError::IllegalMove { line, coordinates } => write!(f, "line {}: {} cannot be played here", line, coordinates),
+            RecordError::UnexpectedEndOfFile => write!(f, "the file ended in the middle of a game")
+        }
+    }
+}
+
+impl Error for RecordError {}
+
+impl From<io::Error> for RecordError {
+    fn from(e: io::Error) -> Self {
+        RecordError::Io(e)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{read_games, write_game, Settings};
+    use crate::game::{Game, Participant};
+    use crate::position::{Coordinates, Evaluation};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_round_trip() {
+        let mut game: Game = "a1 d4 a1".parse().unwrap();
+        game.play(Coordinates::new(1, 1), Participant::Engine, None, Some(Evaluation::HeuristicScore(-4))).unwrap();
+        game.play(Coordinates::new(0, 0), Participant::Human, Some(UNIX_EPOCH + Duration::from_millis(1697712345123)), None).unwrap();
+
+        let mut settings = Settings::new();
+        settings.insert("black".to_string(), "engine 6".to_string());
+
+        let mut buffer = Vec::new();
+        write_game(&mut buffer, &game, &settings).unwrap();
+        write_game(&mut buffer, &Game::new(), &Settings::new()).unwrap();
+
+        let saved_games = read_games(&buffer[..]).unwrap();
+        assert_eq!(saved_games.len(), 2);
+
+        let loaded = &saved_games[0];
+        assert_eq!(loaded.settings.get("black").map(String::as_str), Some("engine 6"));
+        assert_eq!(loaded.game.position(), game.position());
+        assert_eq!(loaded.game.moves().len(), 5);
+        assert_eq!(loaded.game.moves()[3].participant, Participant::Engine);
+        assert_eq!(loaded.game.moves()[3].evaluation, Some(Evaluation::HeuristicScore(-4)));
+        assert_eq!(loaded.game.moves()[3].timestamp, None);
+        assert_eq!(loaded.game.moves()[4].timestamp, game.moves()[4].timestamp);
+
+        assert!(saved_games[1].game.moves().is_empty());
+    }
+
+    #[test]
+    fn test_illegal_move() {
+        let record = "Game\nMove a1 w human 0 ?\nMove a1 w human 0 ?\nResult ongoing\nEnd\n";
+        assert_eq!(read_games(record.as_bytes()).err().map(|e| e.to_string()), Some("line 3: a1 cannot be played here".to_string()));
+    }
+}
@@ -0,0 +1,144 @@
+use yonmokun::position::{Cell, Coordinates, Player, Position};
+
+
+/// How to draw the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RenderOptions {
+    /// Turns each floor around to show the board as seen from the other side of the table.
+    pub flipped: bool,
+    /// Colours the pieces and marks with ANSI escape codes.
+    pub colour: bool,
+    /// Stacks the floors one above the other, for terminals too narrow to show them side by side.
+    pub compact: bool
+}
+
+
+/// The space between floors drawn side by side.
+const FLOOR_GAP: &str = "   ";
+
+const WHITE_COLOUR: &str = "33";
+const BLACK_COLOUR: &str = "36";
+const EMPTY_COLOUR: &str = "2";
+const LAST_MOVE_COLOUR: &str = "1";
+const HIGHLIGHT_COLOUR: &str = "1;32";
+
+
+/// Draws the board with its floors labelled 1F-4F, side by side from the bottom floor up or, in compact mode, stacked
+/// from the top floor down. White is ○, Black is ● and an empty cell is ·. The last move is marked with parentheses
+/// and the highlighted cells, such as those of a winning line, with brackets.
+pub fn render(position: &Position, options: RenderOptions, highlighted: &[Cell]) -> String {
+    let floors: Vec<Vec<String>> = (0..4).map(|floor| render_floor(position, floor, options, highlighted)).collect();
+
+    let mut output = String::from("\n");
+    match options.compact {
+        true => for floor in floors.iter().rev() {
+            for line in floor {
+                output.push_str(line.trim_end());
+                output.push('\n');
+            }
+            output.push('\n');
+        },
+        false => {
+            for i in 0..floors[0].len() {
+                let line: Vec<&str> = floors.iter().map(|floor| floor[i].as_str()).collect();
+                output.push_str(line.join(FLOOR_GAP).trim_end());
+                output.push('\n');
+            }
+            output.push('\n');
+        }
+    }
+
+    output
+}
+
+
+/// Draws one floor as lines of the same width: the label, a row for each rank and the file letters. Each cell takes
+/// three characters, leaving room to mark it on either side.
+fn render_floor(position: &Position, floor: usize, options: RenderOptions, highlighted: &[Cell]) -> Vec<String> {
+    let files: [usize; 4] = if options.flipped { [3, 2, 1, 0] } else { [0, 1, 2, 3] };
+    let ranks: [usize; 4] = if options.flipped { [0, 1, 2, 3] } else { [3, 2, 1, 0] };
+    let last_move = position.last_move();
+
+    let mut lines = vec![format!("{:^13}", format!("{}F", floor+1))];
+    for rank in ranks {
+        let mut line = (rank+1).to_string();
+        for file in files {
+            let cell = Cell::new(Coordinates::new(file, rank), floor);
+            let marks = if highlighted.contains(&cell) {
+                Some(("[", "]", HIGHLIGHT_COLOUR))
+            } else if last_move == Some(cell) {
+                Some(("(", ")", LAST_MOVE_COLOUR))
+            } else {
+                None
+            };
+
+            let (symbol, colour) = match position.piece_at(cell) {
+                Some(Player::White) => ("○", WHITE_COLOUR),
+                Some(Player::Black) => ("●", BLACK_COLOUR),
+                None => ("·", EMPTY_COLOUR)
+            };
+            let symbol = paint(symbol, colour, options.colour);
+
+            match marks {
+                Some((open, close, colour)) => line.push_str(&format!("{}{}{}", paint(open, colour, options.colour), symbol, paint(close, colour, options.colour))),
+                None => line.push_str(&format!(" {} ", symbol))
+            }
+        }
+        lines.push(line);
+    }
+
+    let file_labels: Vec<String> = files.iter().map(|file| format!(" {} ", (b'a' + *file as u8) as char)).collect();
+    lines.push(format!(" {}", file_labels.concat()));
+
+    lines
+}
+
+
+fn paint(text: &str, colour: &str, enabled: bool) -> String {
+    match enabled {
+        true => format!("\x1b[{}m{}\x1b[0m", colour, text),
+        false => text.to_string()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{render, RenderOptions};
+    use yonmokun::position::{Cell, Coordinates, Position};
+
+    fn render_lines(position: &Position, options: RenderOptions, highlighted: &[Cell]) -> Vec<String> {
+        render(position, options, highlighted).lines().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_render() {
+        let position = Position::new().play(Coordinates::new(0, 0)).play(Coordinates::new(3, 3));
+
+        let lines = render_lines(&position, RenderOptions::default(), &[]);
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[1], "     1F              2F              3F              4F");
+        assert_eq!(lines[2], "4 ·  ·  · (●)   4 ·  ·  ·  ·    4 ·  ·  ·  ·    4 ·  ·  ·  ·");
+        assert_eq!(lines[5], "1 ○  ·  ·  ·    1 ·  ·  ·  ·    1 ·  ·  ·  ·    1 ·  ·  ·  ·");
+        assert_eq!(lines[6], "  a  b  c  d      a  b  c  d      a  b  c  d      a  b  c  d");
+
+        let options = RenderOptions { flipped: true, compact: true, ..RenderOptions::default() };
+        let highlighted = [Cell::new(Coordinates::new(0, 0), 0), Cell::new(Coordinates::new(1, 0), 0)];
+        let lines = render_lines(&position, options, &highlighted);
+        assert_eq!(lines.len(), 29);
+        assert_eq!(lines[1], "     4F");
+        assert_eq!(lines[22], "     1F");
+        assert_eq!(lines[23], "1 ·  · [·][○]");
+        assert_eq!(lines[26], "4(●) ·  ·  ·");
+        assert_eq!(lines[27], "  d  c  b  a");
+        assert_eq!(render_lines(&position, RenderOptions { flipped: false, ..options }, &highlighted)[26], "1[○][·] ·  ·");
+    }
+
+    #[test]
+    fn test_colour() {
+        let position = Position::new().play(Coordinates::new(0, 0));
+        let output = render(&position, RenderOptions { colour: true, ..RenderOptions::default() }, &[]);
+        assert!(output.contains("\x1b[1m(\x1b[0m\x1b[33m○\x1b[0m\x1b[1m)\x1b[0m"));
+        assert!(!render(&position, RenderOptions::default(), &[]).contains('\x1b'));
+    }
+}
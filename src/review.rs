@@ -0,0 +1,70 @@
+use yonmokun::evaluation;
+use yonmokun::game::{Game, MoveRecord};
+use yonmokun::position::{Coordinates, Evaluation, EngineError};
+
+
+/// The engine's verdict on one move of a game. Both evaluations are from the point of view of the player who made
+/// the move, so the difference between them is how much the move cost them.
+pub struct MoveReview {
+    pub record: MoveRecord,
+    /// The evaluation of the position before the move, assuming best play.
+    pub best_evaluation: Evaluation,
+    pub best_move: Coordinates,
+    /// The evaluation of the position after the move that was actually played.
+    pub played_evaluation: Evaluation,
+    /// Whether the move threw away a position that was not lost, leaving one that is.
+    pub is_blunder: bool
+}
+
+
+/// Re-analyses every move of a game at the given depth. Returns an error if a move was played after the game was
+/// already over.
+pub fn review_game(game: &Game, depth: u8) -> Result<Vec<MoveReview>, EngineError> {
+    let mut reviews = Vec::new();
+
+    for (i, record) in game.moves().iter().enumerate() {
+        let position_before = game.position_after(i);
+        let report = evaluation::analyse(&position_before, depth)?;
+
+        let position_after = position_before.play(record.coordinates);
+        let played_evaluation = if record.coordinates == report.best_move {
+            report.evaluation
+        } else if position_after.is_terminal() || depth == 0 {
+            -position_after.get_static_evaluation()
+        } else {
+            -evaluation::analyse(&position_after, depth-1)?.evaluation
+        };
+
+        reviews.push(MoveReview {
+            record: *record,
+            best_evaluation: report.evaluation,
+            best_move: report.best_move,
+            played_evaluation,
+            is_blunder: report.evaluation != Evaluation::Loss && played_evaluation == Evaluation::Loss
+        });
+    }
+
+    Ok(reviews)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::review_game;
+    use yonmokun::game::Game;
+    use yonmokun::position::{Coordinates, Evaluation};
+
+    #[test]
+    fn test_blunder_is_flagged() {
+        // White has three in the a1 column; Black fails to block it.
+        let game: Game = "a1 d4 a1 d4 a1 c3 a1".parse().unwrap();
+        let reviews = review_game(&game, 2).unwrap();
+
+        assert_eq!(reviews.len(), 7);
+        assert!(reviews[5].is_blunder);
+        assert_eq!(reviews[5].best_move, Coordinates::new(0, 0));
+        assert_eq!(reviews[5].played_evaluation, Evaluation::Loss);
+        assert!(!reviews[6].is_blunder);
+        assert_eq!(reviews[6].played_evaluation, Evaluation::Win);
+    }
+}
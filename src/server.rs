@@ -0,0 +1,321 @@
+use yonmokun::evaluation::{self, SearchLimits, MAX_DEPTH};
+use yonmokun::game::{Game, GameResult};
+use yonmokun::json::{self, Value};
+use yonmokun::position::{Coordinates, Player};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+
+pub const DEFAULT_PORT: u16 = 8080;
+
+const MAX_BODY_LENGTH: usize = 64 * 1024;
+
+/// The longest the request line or a header may be, counting the line ending.
+const MAX_LINE_LENGTH: usize = 8 * 1024;
+
+/// The longest a single `/analyse` request may search for, whatever it asks for.
+const MAX_SEARCH_TIME: Duration = Duration::from_secs(60);
+
+/// How long a client may keep a connection waiting for the next part of its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+
+/// Serves the HTTP API on the listener, handling each connection on its own thread. Every endpoint takes a JSON
+/// object with a "position" member holding a position string or move list, and answers with a JSON object:
+///
+/// - `POST /validate` reads the position, answering whether it is valid and, if so, its position string, the player
+///   to move and the result if the game is over.
+/// - `POST /legal` also takes a "move" in notation, answering whether it can be played and on which floor it lands.
+/// - `POST /evaluate` answers with the static evaluation for the player to move.
+/// - `POST /analyse` searches the position, optionally with a "depth", "time_ms" or "threads" in place of the
+///   defaults, answering with the fields of the analysis report. No search runs for longer than a minute.
+///
+/// `GET /` identifies the server. Errors are answered with a 4xx status and an "error" member. A connection that
+/// fails is logged and dropped without stopping the server.
+pub fn serve(listener: TcpListener, default_limits: SearchLimits) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("yonmokun: {}", e);
+                continue;
+            }
+        };
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, default_limits) {
+                eprintln!("yonmokun: {}", e);
+            };
+        });
+    }
+
+    Ok(())
+}
+
+
+fn handle_connection(mut stream: TcpStream, default_limits: SearchLimits) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let (status, body) = match read_request(&mut BufReader::new(&stream)) {
+        Ok(request) => respond(&request, default_limits),
+        Err(e) => (400, error_body(&e))
+    };
+
+    write!(stream, "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, reason_phrase(status), body.len(), body)?;
+    stream.flush()
+}
+
+
+struct Request {
+    method: String,
+    path: String,
+    body: String
+}
+
+
+fn read_request<R: BufRead>(reader: &mut R) -> Result<Request, String> {
+    let request_line = read_line(reader)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err("malformed request line".to_string())
+    };
+
+    let mut content_length = 0;
+    loop {
+        let header = read_line(reader)?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        };
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().map_err(|_| "invalid Content-Length".to_string())?;
+            };
+        };
+    }
+
+    if content_length > MAX_BODY_LENGTH {
+        return Err("request body too large".to_string())
+    };
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(|e| e.to_string())?;
+    let body = String::from_utf8(body).map_err(|_| "request body is not UTF-8".to_string())?;
+
+    Ok(Request { method: method.to_string(), path: path.to_string(), body })
+}
+
+
+/// Reads a line of the request head, giving up on lines longer than `MAX_LINE_LENGTH`. The end of the input reads as
+/// an empty line.
+fn read_line<R: BufRead>(reader: &mut R) -> Result<String, String> {
+    let mut line = String::new();
+    reader.by_ref().take(MAX_LINE_LENGTH as u64).read_line(&mut line).map_err(|e| e.to_string())?;
+    match line.len() == MAX_LINE_LENGTH && !line.ends_with('\n') {
+        true => Err("request line or header too long".to_string()),
+        false => Ok(line)
+    }
+}
+
+
+/// Answers a request with a status code and a JSON body.
+fn respond(request: &Request, default_limits: SearchLimits) -> (u16, String) {
+    let endpoint: fn(&Value, SearchLimits) -> Result<String, String> = match request.path.as_str() {
+        "/" if request.method == "GET" => return (200, format!("{{\"name\": \"yonmokun\", \"version\": {}}}", json::string(env!("CARGO_PKG_VERSION")))),
+        "/validate" => validate,
+        "/legal" => legal,
+        "/evaluate" => evaluate,
+        "/analyse" => analyse,
+        "/" => return (405, error_body("method not allowed")),
+        _ => return (404, error_body("not found"))
+    };
+
+    if request.method != "POST" {
+        return (405, error_body("method not allowed"))
+    };
+
+    let arguments = match json::parse(&request.body) {
+        Ok(arguments @ Value::Object(_)) => arguments,
+        Ok(_) => return (400, error_body("expected a JSON object")),
+        Err(e) => return (400, error_body(&e.to_string()))
+    };
+
+    match endpoint(&arguments, default_limits) {
+        Ok(body) => (200, body),
+        Err(e) => (400, error_body(&e))
+    }
+}
+
+
+fn read_game(arguments: &Value) -> Result<Game, String> {
+    let position = arguments.get("position").and_then(Value::as_str).ok_or("expected a \"position\" string")?;
+    Game::from_position_or_moves(position).map_err(|e| e.to_string())
+}
+
+
+fn validate(arguments: &Value, _: SearchLimits) -> Result<String, String> {
+    let game = match read_game(arguments) {
+        Ok(game) => game,
+        Err(e) => return Ok(format!("{{\"valid\": false, \"error\": {}}}", json::string(&e)))
+    };
+
+    let result = match game.result() {
+        None => "null",
+        Some(GameResult::Win(Player::White)) => "\"w\"",
+        Some(GameResult::Win(Player::Black)) => "\"b\"",
+        Some(GameResult::Draw) => "\"draw\""
+    };
+
+    Ok(format!(
+        "{{\"valid\": true, \"position\": {}, \"to_move\": \"{}\", \"result\": {}}}",
+        json::string(&game.position().to_string()),
+        game.position().active_player,
+        result
+    ))
+}
+
+
+fn legal(arguments: &Value, _: SearchLimits) -> Result<String, String> {
+    let game = read_game(arguments)?;
+    let notation = arguments.get("move").and_then(Value::as_str).ok_or("expected a \"move\" string")?;
+    let (coordinates, floor) = Coordinates::parse_with_floor(notation).map_err(|e| e.to_string())?;
+
+    let position = game.position();
+    let illegal = |reason: &str| Ok(format!("{{\"legal\": false, \"reason\": {}}}", json::string(reason)));
+    if position.is_terminal() {
+        return illegal("the game is already over")
+    };
+    let Some(landing_cell) = position.landing_cell(coordinates) else {
+        return illegal("the column is full")
+    };
+
+    if floor.is_some_and(|floor| floor != landing_cell.floor()) {
+        return illegal(&format!("the move lands on floor {}", landing_cell.floor()+1))
+    };
+
+    Ok(format!("{{\"legal\": true, \"floor\": {}}}", landing_cell.floor()+1))
+}
+
+
+fn evaluate(arguments: &Value, _: SearchLimits) -> Result<String, String> {
+    let game = read_game(arguments)?;
+    let position = game.position();
+    let evaluation = position.get_static_evaluation();
+
+    Ok(format!(
+        "{{\"to_move\": \"{}\", \"evaluation\": \"{}\", \"confidence\": {:.3}, \"terminal\": {}}}",
+        position.active_player,
+        evaluation,
+        evaluation.to_confidence(),
+        position.is_terminal()
+    ))
+}
+
+
+fn analyse(arguments: &Value, default_limits: SearchLimits) -> Result<String, String> {
+    let game = read_game(arguments)?;
+    let position = game.position();
+    let limits = search_limits(arguments, default_limits)?;
+
+    let report = evaluation::analyse_with_limits(position, limits).map_err(|e| e.to_string())?;
+    Ok(format!("{{\"to_move\": \"{}\", {}}}", position.active_player, json::report_members(&report)))
+}
+
+
+/// Reads the limits for `/analyse`, falling back on the defaults. However deep the search is asked to go, it never
+/// runs for longer than `MAX_SEARCH_TIME`.
+fn search_limits(arguments: &Value, default_limits: SearchLimits) -> Result<SearchLimits, String> {
+    let number = |key: &str, max: f64| match arguments.get(key) {
+        None => Ok(None),
+        Some(value) => value.as_f64().filter(|n| n.fract() == 0.0 && (1.0..=max).contains(n)).map(Some).ok_or(format!("\"{}\" should be a whole number from 1 to {}", key, max))
+    };
+
+    let time_budget = number("time_ms", MAX_SEARCH_TIME.as_millis() as f64)?.map(|ms| Duration::from_millis(ms as u64));
+    Ok(SearchLimits {
+        depth: match (number("depth", MAX_DEPTH as f64)?, time_budget) {
+            (Some(depth), _) => depth as u8,
+            (None, Some(_)) => MAX_DEPTH,
+            (None, None) => default_limits.depth
+        },
+        time_budget: Some(time_budget.or(default_limits.time_budget).map_or(MAX_SEARCH_TIME, |time_budget| time_budget.min(MAX_SEARCH_TIME))),
+        threads: number("threads", thread::available_parallelism().map_or(1, |n| n.get()) as f64)?.map_or(default_limits.threads, |threads| threads as usize)
+    })
+}
+
+
+fn error_body(message: &str) -> String {
+    format!("{{\"error\": {}}}", json::string(message))
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => ""
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{read_request, respond, search_limits, Request, MAX_SEARCH_TIME};
+    use crate::batch::DEFAULT_LIMITS;
+    use yonmokun::json::{self, Value};
+    use std::time::Duration;
+
+    fn post(path: &str, body: &str) -> (u16, Value) {
+        let request = Request { method: "POST".to_string(), path: path.to_string(), body: body.to_string() };
+        let (status, body) = respond(&request, DEFAULT_LIMITS);
+        (status, json::parse(&body).unwrap())
+    }
+
+    #[test]
+    fn test_endpoints() {
+        let (status, body) = post("/validate", r#"{"position": "a1 d4 a1"}"#);
+        assert_eq!(status, 200);
+        assert_eq!(body.get("valid"), Some(&Value::Bool(true)));
+        assert_eq!(body.get("to_move").and_then(Value::as_str), Some("b"));
+
+        let (_, body) = post("/validate", r#"{"position": "a1 e5"}"#);
+        assert_eq!(body.get("valid"), Some(&Value::Bool(false)));
+
+        let (_, body) = post("/legal", r#"{"position": "a1 a1", "move": "a1:3"}"#);
+        assert_eq!(body.get("legal"), Some(&Value::Bool(true)));
+        let (_, body) = post("/legal", r#"{"position": "a1 a1", "move": "a1:2"}"#);
+        assert_eq!(body.get("legal"), Some(&Value::Bool(false)));
+
+        let (_, body) = post("/evaluate", r#"{"position": "a1 d4 a1 d4 a1 d4 a1"}"#);
+        assert_eq!(body.get("evaluation").and_then(Value::as_str), Some("loss"));
+
+        let (status, body) = post("/analyse", r#"{"position": "a1 d4 a1 d4 a1 d4", "depth": 2}"#);
+        assert_eq!(status, 200);
+        assert_eq!(body.get("best_move").and_then(Value::as_str), Some("a1"));
+        assert_eq!(body.get("evaluation").and_then(Value::as_str), Some("win"));
+
+        let (_, body) = post("/analyse", r#"{"position": "a1 d4", "depth": 2}"#);
+        assert_eq!(body.get("depth").and_then(Value::as_f64), Some(2.0));
+
+        assert_eq!(post("/analyse", r#"{"position": "a1", "depth": 0}"#).0, 400);
+
+        let limits = search_limits(&json::parse(r#"{"position": "", "depth": 64}"#).unwrap(), DEFAULT_LIMITS).unwrap();
+        assert_eq!((limits.depth, limits.time_budget), (64, Some(MAX_SEARCH_TIME)));
+        let limits = search_limits(&json::parse(r#"{"position": "", "time_ms": 500}"#).unwrap(), DEFAULT_LIMITS).unwrap();
+        assert_eq!(limits.time_budget, Some(Duration::from_millis(500)));
+        assert_eq!(post("/analyse", "[]").0, 400);
+        assert_eq!(post("/solve", "{}").0, 404);
+    }
+
+    #[test]
+    fn test_read_request() {
+        let raw = "POST /legal HTTP/1.1\r\nHost: localhost\r\ncontent-length: 2\r\n\r\n{}";
+        let request = read_request(&mut raw.as_bytes()).unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str(), request.body.as_str()), ("POST", "/legal", "{}"));
+
+        assert!(read_request(&mut "\r\n".as_bytes()).is_err());
+        assert!(read_request(&mut "GET / HTTP/1.1\r\n".as_bytes()).is_ok());
+        assert_eq!(read_request(&mut "a".repeat(1 << 20).as_bytes()).err().as_deref(), Some("request line or header too long"));
+    }
+}
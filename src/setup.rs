@@ -0,0 +1,96 @@
+use yonmokun::difficulty::MAX_LEVEL;
+use yonmokun::position::Player;
+use yonmokun::record::Settings;
+
+
+/// Who makes the moves for one side of the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Controller {
+    Human,
+    Engine { level: u8 }
+}
+
+
+/// How a game in the CLI is being played: who controls each side, whether the engine offers hints to human players
+/// and whether engine moves are played without waiting for the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Setup {
+    pub white: Controller,
+    pub black: Controller,
+    pub show_hints: bool,
+    pub auto_play: bool
+}
+
+impl Setup {
+    pub fn controller(&self, player: Player) -> Controller {
+        match player {
+            Player::White => self.white,
+            Player::Black => self.black
+        }
+    }
+
+    pub fn num_humans(&self) -> usize {
+        [self.white, self.black].iter().filter(|controller| **controller == Controller::Human).count()
+    }
+
+    pub fn to_settings(self) -> Settings {
+        let mut settings = Settings::new();
+        settings.insert("white".to_string(), controller_to_setting(self.white));
+        settings.insert("black".to_string(), controller_to_setting(self.black));
+        settings.insert("hints".to_string(), self.show_hints.to_string());
+        settings.insert("auto_play".to_string(), self.auto_play.to_string());
+        settings
+    }
+
+    /// Reads a setup back from saved settings, or returns `None` if either side's controller is missing or invalid.
+    pub fn from_settings(settings: &Settings) -> Option<Setup> {
+        let flag = |key: &str| settings.get(key).map(|value| value == "true").unwrap_or(false);
+
+        Some(Setup {
+            white: controller_from_setting(settings.get("white")?)?,
+            black: controller_from_setting(settings.get("black")?)?,
+            show_hints: flag("hints"),
+            auto_play: flag("auto_play")
+        })
+    }
+}
+
+
+fn controller_to_setting(controller: Controller) -> String {
+    match controller {
+        Controller::Human => "human".to_string(),
+        Controller::Engine { level } => format!("engine {}", level)
+    }
+}
+
+fn controller_from_setting(s: &str) -> Option<Controller> {
+    match s.split_once(' ') {
+        None if s == "human" => Some(Controller::Human),
+        Some(("engine", level)) => level.parse().ok().filter(|level| (1..=MAX_LEVEL).contains(level)).map(|level| Controller::Engine { level }),
+        _ => None
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{Controller, Setup};
+    use yonmokun::record;
+
+    #[test]
+    fn test_settings_round_trip() {
+        let setup = Setup { white: Controller::Engine { level: 4 }, black: Controller::Human, show_hints: true, auto_play: false };
+        assert_eq!(Setup::from_settings(&setup.to_settings()), Some(setup));
+
+        let mut settings = setup.to_settings();
+        settings.insert("white".to_string(), "engine 9".to_string());
+        assert_eq!(Setup::from_settings(&settings), None);
+        settings.remove("white");
+        assert_eq!(Setup::from_settings(&settings), None);
+
+        // The example in the documentation of the record format.
+        let record = "Game\nSetting auto_play false\nSetting black engine 4\nSetting hints false\nSetting white human\nResult ongoing\nEnd\n";
+        let saved_game = record::read_games(record.as_bytes()).unwrap().remove(0);
+        assert_eq!(Setup::from_settings(&saved_game.settings), Some(Setup { white: Controller::Human, black: Controller::Engine { level: 4 }, show_hints: false, auto_play: false }));
+    }
+}
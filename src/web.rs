@@ -0,0 +1,152 @@
+use crate::evaluation::{self, Clock, SearchLimits, StopHandle, MAX_DEPTH};
+use crate::game;
+use crate::json;
+use std::time::Duration;
+
+
+// A thin facade over the engine for a browser build. Every function takes string slices and numbers and answers with
+// a JSON object in a `String`, so that a binding layer such as wasm-bindgen can export it to JavaScript with little
+// glue. This crate does not export anything itself: only numbers cross the raw WebAssembly boundary, and strings need
+// that glue to copy them in and out of the module's memory. Positions are given as a position string or a move list,
+// and the answers take the same shape as in the binary's HTTP API. Nothing here spawns threads or reads the system
+// clock, neither of which a browser provides: searches tell the time by the host's clock, which the JavaScript that
+// instantiates the module passes in as the import `env.now_ms`, such as `performance.now()`.
+//
+// The tests only run natively, so check that the facade still builds for the browser with
+// `cargo build --lib --target wasm32-unknown-unknown --features wasm`.
+
+
+/// A clock that asks the host for the time in milliseconds.
+pub struct MillisecondClock(pub fn() -> f64);
+
+impl Clock for MillisecondClock {
+    fn now(&self) -> Duration {
+        Duration::from_secs_f64(((self.0)() / 1000.0).max(0.0))
+    }
+}
+
+
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+    fn now_ms() -> f64;
+}
+
+#[cfg(target_arch = "wasm32")]
+fn host_clock() -> impl Clock {
+    // SAFETY: `now_ms` takes nothing and returns a number, so any function the host provides for it is sound to call.
+    MillisecondClock(|| unsafe { now_ms() })
+}
+
+/// Natively, as when testing, the host's clock is the system clock.
+#[cfg(not(target_arch = "wasm32"))]
+fn host_clock() -> impl Clock {
+    evaluation::SystemClock::new()
+}
+
+
+/// Answers whether the position is valid and, if so, its position string and the player to move.
+pub fn validate_position(position: &str) -> String {
+    match game::read_position(position) {
+        Ok(position) => format!(
+            "{{\"valid\": true, \"position\": \"{}\", \"to_move\": \"{}\"}}",
+            position,
+            position.active_player
+        ),
+        Err(e) => format!("{{\"valid\": false, \"error\": {}}}", json::string(&e.to_string()))
+    }
+}
+
+
+/// Answers with the moves that can be played in the position, in notation.
+pub fn legal_moves(position: &str) -> String {
+    let position = match game::read_position(position) {
+        Ok(position) => position,
+        Err(e) => return error_body(&e.to_string())
+    };
+
+    let moves: Vec<String> = match position.is_terminal() {
+        true => Vec::new(),
+        false => position.generate_moves().iter().map(|coordinates| format!("\"{}\"", coordinates)).collect()
+    };
+    format!("{{\"moves\": [{}]}}", moves.join(", "))
+}
+
+
+/// Answers with the static evaluation of the position for the player to move.
+pub fn static_evaluation(position: &str) -> String {
+    let position = match game::read_position(position) {
+        Ok(position) => position,
+        Err(e) => return error_body(&e.to_string())
+    };
+
+    let evaluation = position.get_static_evaluation();
+    format!("{{\"evaluation\": \"{}\", \"confidence\": {:.3}}}", evaluation, evaluation.to_confidence())
+}
+
+
+/// Searches the position on a single thread to the given depth or, if `time_ms` is not 0, for as long as it allows.
+/// Answers with the fields of the analysis report.
+pub fn analyse(position: &str, depth: u8, time_ms: u32) -> String {
+    let position = match game::read_position(position) {
+        Ok(position) => position,
+        Err(e) => return error_body(&e.to_string())
+    };
+
+    let time_budget = match time_ms {
+        0 => None,
+        ms => Some(Duration::from_millis(ms as u64))
+    };
+    let limits = SearchLimits {
+        depth: match (depth, time_budget) {
+            (0, Some(_)) => MAX_DEPTH,
+            (depth, _) => depth.clamp(1, MAX_DEPTH)
+        },
+        time_budget,
+        threads: 1
+    };
+
+    match evaluation::analyse_with_clock(&position, limits, &host_clock(), &StopHandle::new(), &mut |_| {}) {
+        Ok(report) => format!("{{{}}}", json::report_members(&report)),
+        Err(e) => error_body(&e.to_string())
+    }
+}
+
+
+fn error_body(message: &str) -> String {
+    format!("{{\"error\": {}}}", json::string(message))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{analyse, legal_moves, static_evaluation, validate_position, MillisecondClock};
+    use crate::evaluation::Clock;
+    use std::time::Duration;
+
+    #[test]
+    fn test_millisecond_clock() {
+        let clock = MillisecondClock(|| 1500.0);
+        assert_eq!(clock.now(), Duration::from_millis(1500));
+        assert_eq!(MillisecondClock(|| -1.0).now(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_facade() {
+        assert_eq!(validate_position("a1 d4"), "{\"valid\": true, \"position\": \"w..............b/................/................/................ w 2\", \"to_move\": \"w\"}");
+        assert!(validate_position("a1 e5").starts_with("{\"valid\": false, \"error\": \"move 2: "));
+
+        let moves = legal_moves("a1 a1 a1 a1");
+        assert_eq!(moves.matches(", ").count(), 14);
+        assert!(!moves.contains("\"a1\"") && moves.contains("\"d4\""));
+
+        assert!(static_evaluation("a1 d4 a1 d4 a1 d4 a1").starts_with("{\"evaluation\": \"loss\""));
+
+        let body = analyse("a1 d4 a1 d4 a1 d4", 2, 0);
+        assert!(body.starts_with("{\"best_move\": \"a1\", \"evaluation\": \"win\""));
+        assert!(body.contains("\"depth\": 2,"));
+
+        let body = analyse("a1", 0, 20);
+        let time_ms: u64 = body.split("\"time_ms\": ").nth(1).and_then(|ms| ms.trim_end_matches('}').parse().ok()).unwrap();
+        assert!(time_ms >= 20);
+    }
+}
@@ -0,0 +1,18 @@
+use yonmokun::{analyse, Coordinates, Evaluation, Player, Position};
+
+
+#[test]
+fn test_public_api() {
+    let mut position = Position::new();
+    for notation in ["a1", "d4", "a1", "d4", "a1", "d4"] {
+        position.make_move(notation.parse::<Coordinates>().unwrap());
+    }
+    assert_eq!(position.active_player, Player::White);
+
+    let report = analyse(&position, 2).unwrap();
+    assert_eq!(report.best_move, Coordinates::new(0, 0));
+    assert_eq!(report.evaluation, Evaluation::Win);
+
+    let position: Position = position.to_string().parse().unwrap();
+    assert_eq!(position.play(report.best_move).get_static_evaluation(), Evaluation::Loss);
+}